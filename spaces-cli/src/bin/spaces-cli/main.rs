@@ -1,9 +1,16 @@
-use std::error;
+use std::{
+    error, fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use clap::{Parser, Subcommand};
 use jsonrpc_core::futures;
 use spacesvm::{
-    api::client::{claim_tx, delete_tx, get_or_create_pk, set_tx, Client, Uri},
+    api::client::{
+        append_tx, batch_tx, cas_tx, claim_tx, delete_space_tx, delete_tx, get_or_create_pk,
+        grant_tx, increment_tx, lifeline_tx, move_tx, multisig_tx, read_pk, revoke_tx,
+        set_expiring_tx, set_tx, transfer_tx, with_value_meta, Client, Uri,
+    },
     chain::tx::unsigned::TransactionData,
 };
 
@@ -12,19 +19,113 @@ enum Command {
     Claim {
         space: String,
     },
+    /// Sets the key. With --ttl the key is removed that many seconds later.
+    /// The MIME type and "name=value" attributes of the value are returned
+    /// when it is resolved.
     Set {
         space: String,
         key: String,
         value: String,
+        #[clap(long)]
+        ttl: Option<u64>,
+        #[clap(long, default_value = "")]
+        content_type: String,
+        #[clap(long)]
+        attribute: Vec<String>,
     },
     Delete {
         space: String,
         key: String,
     },
+    /// Appends the value to the key, creating the key if it is missing.
+    Append {
+        space: String,
+        key: String,
+        value: String,
+    },
+    /// Adds the amount to the counter stored at the key, an 8 byte big
+    /// endian integer which starts at zero.
+    Increment {
+        space: String,
+        key: String,
+        amount: u64,
+    },
+    /// Releases the space together with all of its keys.
+    DeleteSpace {
+        space: String,
+    },
     Get {
         space: String,
         key: String,
     },
+    /// Uploads a file to <space>/<key> as content addressed chunks and a
+    /// manifest listing them.
+    SetFile {
+        path: String,
+        file: String,
+    },
+    /// Downloads the file stored at <space>/<key>, verifying every chunk.
+    ResolveFile {
+        path: String,
+        destination: String,
+    },
+    Transfer {
+        to: String,
+        units: u64,
+    },
+    Move {
+        space: String,
+        to: String,
+    },
+    Lifeline {
+        space: String,
+        units: u64,
+    },
+    /// Applies all ops to the space in a single transaction. Each op is
+    /// either "key=value" to set a key or "key" to delete it.
+    Batch {
+        space: String,
+        #[clap(required = true)]
+        ops: Vec<String>,
+    },
+    /// Sets the key, or deletes it without a value, only if it still
+    /// references the expected tx id. Without one the key must not exist.
+    Cas {
+        space: String,
+        key: String,
+        value: Option<String>,
+        #[clap(long)]
+        expected: Option<String>,
+    },
+    /// Allows an address to write keys starting with the prefix until the
+    /// expiry, a unix timestamp or zero for no expiry.
+    Grant {
+        space: String,
+        to: String,
+        #[clap(long, default_value = "")]
+        prefix: String,
+        #[clap(long, default_value_t = 0)]
+        expiry: u64,
+    },
+    Revoke {
+        space: String,
+        to: String,
+    },
+    /// Makes the space owned by threshold of the member addresses. Txs
+    /// acting as its owner must then be cosigned with --cosigner-key-file.
+    /// Without members the sender becomes the single owner again.
+    Multisig {
+        space: String,
+        #[clap(long, default_value_t = 0)]
+        threshold: u64,
+        members: Vec<String>,
+    },
+    Balance {
+        address: String,
+    },
+    Info {
+        space: String,
+    },
     Ping {},
 }
 
@@ -39,6 +140,10 @@ struct Cli {
     #[clap(long, default_value = ".spacesvm-cli-pk")]
     private_key_file: String,
 
+    /// Private key files of multisig members which cosign the tx.
+    #[clap(long)]
+    cosigner_key_file: Vec<String>,
+
     /// Which subcommand to call.
     #[command(subcommand)]
     command: Command,
@@ -63,6 +168,32 @@ async fn main() -> Result<(), Box<dyn error::Error>> {
         return Ok(());
     }
 
+    if let Command::ResolveFile { path, destination } = &cli.command {
+        let (space, key) = parse_path(path)?;
+        let data = client
+            .resolve_file(space, key)
+            .await
+            .map_err(|e| e.to_string())?;
+        fs::write(destination, &data)?;
+
+        println!("wrote {} bytes to {}", data.len(), destination);
+        return Ok(());
+    }
+
+    if let Command::Balance { address } = &cli.command {
+        let resp = client.balance(address).await.map_err(|e| e.to_string())?;
+
+        println!("{}", serde_json::to_string(&resp)?);
+        return Ok(());
+    }
+
+    if let Command::Info { space } = &cli.command {
+        let resp = client.info(space).await.map_err(|e| e.to_string())?;
+
+        println!("{}", serde_json::to_string(&resp)?);
+        return Ok(());
+    }
+
     if let Command::Ping {} = &cli.command {
         let resp = client.ping().await.map_err(|e| e.to_string())?;
 
@@ -70,6 +201,24 @@ async fn main() -> Result<(), Box<dyn error::Error>> {
         return Ok(());
     }
 
+    let cosigners = cli
+        .cosigner_key_file
+        .iter()
+        .map(|path| read_pk(path))
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    if let Command::SetFile { path, file } = &cli.command {
+        let (space, key) = parse_path(path)?;
+        let data = fs::read(file)?;
+        let resp = client
+            .set_file(space, key, &data, &cosigners)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        println!("{}", serde_json::to_string(&resp)?);
+        return Ok(());
+    }
+
     // decode tx
     let tx_data = command_to_tx(cli.command)?;
     let resp = futures::executor::block_on(client.decode_tx(tx_data)).map_err(|e| e.to_string())?;
@@ -78,7 +227,7 @@ async fn main() -> Result<(), Box<dyn error::Error>> {
 
     // issue tx
     let resp = client
-        .issue_tx(typed_data)
+        .issue_cosigned_tx(typed_data, &cosigners)
         .await
         .map_err(|e| e.to_string())?;
     println!("{}", serde_json::to_string(&resp)?);
@@ -90,11 +239,65 @@ async fn main() -> Result<(), Box<dyn error::Error>> {
 fn command_to_tx(command: Command) -> std::io::Result<TransactionData> {
     match command {
         Command::Claim { space } => Ok(claim_tx(&space)),
-        Command::Set { space, key, value } => Ok(set_tx(&space, &key, &value)),
+        Command::Set {
+            space,
+            key,
+            value,
+            ttl,
+            content_type,
+            attribute,
+        } => {
+            let tx = match ttl {
+                None => set_tx(&space, &key, &value),
+                Some(ttl) => {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?
+                        .as_secs();
+                    set_expiring_tx(&space, &key, &value, now + ttl)
+                }
+            };
+            with_value_meta(tx, &content_type, &attribute)
+        }
         Command::Delete { space, key } => Ok(delete_tx(&space, &key)),
+        Command::Append { space, key, value } => Ok(append_tx(&space, &key, &value)),
+        Command::Increment { space, key, amount } => Ok(increment_tx(&space, &key, amount)),
+        Command::DeleteSpace { space } => Ok(delete_space_tx(&space)),
+        Command::Transfer { to, units } => transfer_tx(&to, units),
+        Command::Move { space, to } => move_tx(&space, &to),
+        Command::Lifeline { space, units } => Ok(lifeline_tx(&space, units)),
+        Command::Batch { space, ops } => Ok(batch_tx(&space, &ops)),
+        Command::Cas {
+            space,
+            key,
+            value,
+            expected,
+        } => cas_tx(&space, &key, expected.as_deref(), value.as_deref()),
+        Command::Grant {
+            space,
+            to,
+            prefix,
+            expiry,
+        } => grant_tx(&space, &to, &prefix, expiry),
+        Command::Revoke { space, to } => revoke_tx(&space, &to),
+        Command::Multisig {
+            space,
+            threshold,
+            members,
+        } => multisig_tx(&space, threshold, &members),
         _ => Err(std::io::Error::new(
             std::io::ErrorKind::Other,
             "not a supported tx",
         )),
     }
 }
+
+/// Splits a "<space>/<key>" path.
+fn parse_path(path: &str) -> std::io::Result<(&str, &str)> {
+    path.split_once('/').ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("path is not <space>/<key>: {}", path),
+        )
+    })
+}
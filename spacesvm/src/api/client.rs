@@ -2,15 +2,17 @@ use std::{
     fs::File,
     io::{Error, ErrorKind, Result, Write},
     path::Path,
+    str::FromStr,
     sync::Arc,
 };
 
 use crate::{
     api::{
-        DecodeTxArgs, DecodeTxResponse, IssueTxArgs, IssueTxResponse, PingResponse, ResolveArgs,
-        ResolveResponse,
+        BalanceArgs, BalanceResponse, DecodeTxArgs, DecodeTxResponse, InfoArgs, InfoResponse,
+        IssueTxArgs, IssueTxResponse, PingResponse, ResolveArgs, ResolveResponse,
     },
     chain::tx::{
+        batch,
         decoder::{self, TypedData},
         tx::TransactionType,
         unsigned::TransactionData,
@@ -110,7 +112,10 @@ impl Client<HttpConnector> {
 
     /// Returns a DecodeTxResponse from client request.
     pub async fn decode_tx(&self, tx_data: TransactionData) -> Result<DecodeTxResponse> {
-        let arg_value = serde_json::to_value(&DecodeTxArgs { tx_data })?;
+        let arg_value = serde_json::to_value(&DecodeTxArgs {
+            tx_data,
+            cosigners: vec![],
+        })?;
         let (_id, json_request) = self
             .raw_request("decodeTx", &Params::Array(vec![arg_value]))
             .await?;
@@ -121,13 +126,37 @@ impl Client<HttpConnector> {
 
     /// Returns a IssueTxResponse from client request.
     pub async fn issue_tx(&self, typed_data: &TypedData) -> Result<IssueTxResponse> {
-        let dh = decoder::hash_structured_data(typed_data)?;
-        let sig = self.sign_digest(&dh.as_bytes()).await?.to_bytes().to_vec();
+        self.issue_cosigned_tx(typed_data, &[]).await
+    }
+
+    /// Returns a IssueTxResponse from client request. The tx is also signed
+    /// with each of the [cosigners] keys, as needed to act as the owner of a
+    /// multisig space.
+    pub async fn issue_cosigned_tx(
+        &self,
+        typed_data: &TypedData,
+        cosigners: &[Key],
+    ) -> Result<IssueTxResponse> {
+        // the sender and the cosigners all sign the typed data including
+        // the cosigners
+        let addresses: Vec<_> = cosigners
+            .iter()
+            .map(|cosigner| cosigner.to_public_key().to_h160())
+            .collect();
+        let typed_data = typed_data.to_owned().with_cosigners(&addresses);
+        let dh = decoder::hash_structured_data(&typed_data)?;
+
+        let mut cosignatures = Vec::with_capacity(cosigners.len());
+        for cosigner in cosigners.iter() {
+            cosignatures.push(cosigner.sign_digest(dh.as_bytes())?.to_bytes().to_vec());
+        }
+        let sig = self.sign_digest(dh.as_bytes()).await?.to_bytes().to_vec();
         log::debug!("signature: {:?}", sig);
 
         let arg_value = serde_json::to_value(&IssueTxArgs {
-            typed_data: typed_data.to_owned(),
+            typed_data,
             signature: sig,
+            cosignatures,
         })?;
         let (_id, json_request) = self
             .raw_request("issueTx", &Params::Array(vec![arg_value]))
@@ -151,6 +180,32 @@ impl Client<HttpConnector> {
         Ok(resp)
     }
 
+    /// Returns a BalanceResponse from client request.
+    pub async fn balance(&self, address: &str) -> Result<BalanceResponse> {
+        let arg_value = serde_json::to_value(&BalanceArgs {
+            address: parse_address(address)?,
+        })?;
+        let (_id, json_request) = self
+            .raw_request("balance", &Params::Array(vec![arg_value]))
+            .await?;
+        let resp = self.post_de::<BalanceResponse>(&json_request).await?;
+
+        Ok(resp)
+    }
+
+    /// Returns an InfoResponse with the usage and quota of a space.
+    pub async fn info(&self, space: &str) -> Result<InfoResponse> {
+        let arg_value = serde_json::to_value(&InfoArgs {
+            space: space.as_bytes().to_vec(),
+        })?;
+        let (_id, json_request) = self
+            .raw_request("info", &Params::Array(vec![arg_value]))
+            .await?;
+        let resp = self.post_de::<InfoResponse>(&json_request).await?;
+
+        Ok(resp)
+    }
+
     /// Returns a deserialized response from client request.
     pub async fn post_de<T: de::DeserializeOwned>(&self, json: &str) -> Result<T> {
         let inner = self.inner.read().await;
@@ -204,6 +259,7 @@ pub fn claim_tx(space: &str) -> TransactionData {
         space: space.to_owned(),
         key: String::new(),
         value: vec![],
+        ..Default::default()
     }
 }
 
@@ -213,18 +269,208 @@ pub fn set_tx(space: &str, key: &str, value: &str) -> TransactionData {
         space: space.to_owned(),
         key: key.to_owned(),
         value: value.as_bytes().to_vec(),
+        ..Default::default()
+    }
+}
+
+/// Sets [key] until [expiry], a unix timestamp after which it is treated as
+/// missing and removed.
+pub fn set_expiring_tx(space: &str, key: &str, value: &str, expiry: u64) -> TransactionData {
+    TransactionData {
+        typ: TransactionType::Set,
+        space: space.to_owned(),
+        key: key.to_owned(),
+        value: value.as_bytes().to_vec(),
+        expiry,
+        ..Default::default()
     }
 }
 
+/// Attaches a MIME [content_type] and "name=value" [attributes] to the
+/// value of a set.
+pub fn with_value_meta(
+    mut tx: TransactionData,
+    content_type: &str,
+    attributes: &[String],
+) -> Result<TransactionData> {
+    tx.content_type = content_type.to_owned();
+    for attribute in attributes.iter() {
+        let (name, value) = attribute.split_once('=').ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("attribute is not name=value: {}", attribute),
+            )
+        })?;
+        tx.attributes.insert(name.to_owned(), value.to_owned());
+    }
+    Ok(tx)
+}
+
 pub fn delete_tx(space: &str, key: &str) -> TransactionData {
     TransactionData {
         typ: TransactionType::Delete,
         space: space.to_owned(),
         key: key.to_owned(),
         value: vec![],
+        ..Default::default()
+    }
+}
+
+pub fn transfer_tx(to: &str, units: u64) -> Result<TransactionData> {
+    Ok(TransactionData {
+        typ: TransactionType::Transfer,
+        to: parse_address(to)?,
+        units,
+        ..Default::default()
+    })
+}
+
+pub fn move_tx(space: &str, to: &str) -> Result<TransactionData> {
+    Ok(TransactionData {
+        typ: TransactionType::Move,
+        space: space.to_owned(),
+        to: parse_address(to)?,
+        ..Default::default()
+    })
+}
+
+pub fn lifeline_tx(space: &str, units: u64) -> TransactionData {
+    TransactionData {
+        typ: TransactionType::Lifeline,
+        space: space.to_owned(),
+        units,
+        ..Default::default()
+    }
+}
+
+/// Creates a batch of ops on [space]. Each op is either "key=value" to set
+/// the key or "key" to delete it.
+pub fn batch_tx(space: &str, ops: &[String]) -> TransactionData {
+    let ops = ops
+        .iter()
+        .map(|op| match op.split_once('=') {
+            Some((key, value)) => batch::Op::Set {
+                key: key.to_owned(),
+                value: value.as_bytes().to_vec(),
+            },
+            None => batch::Op::Delete { key: op.to_owned() },
+        })
+        .collect();
+    TransactionData {
+        typ: TransactionType::Batch,
+        space: space.to_owned(),
+        ops,
+        ..Default::default()
+    }
+}
+
+/// Creates a conditional write of [key]. The key is set to [value], or
+/// deleted if there is none, only if it still references the [expected] tx
+/// id. Without an expected tx id the key must not exist.
+pub fn cas_tx(
+    space: &str,
+    key: &str,
+    expected: Option<&str>,
+    value: Option<&str>,
+) -> Result<TransactionData> {
+    let expected = match expected {
+        Some(id) => avalanche_types::ids::Id::from_str(id).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid tx id {}: {}", id, e),
+            )
+        })?,
+        None => avalanche_types::ids::Id::empty(),
+    };
+    Ok(TransactionData {
+        typ: TransactionType::Cas,
+        space: space.to_owned(),
+        key: key.to_owned(),
+        value: value.map(|v| v.as_bytes().to_vec()).unwrap_or_default(),
+        expected,
+        delete: value.is_none(),
+        ..Default::default()
+    })
+}
+
+/// Allows [to] to write the keys of [space] starting with [prefix] until
+/// [expiry], a unix timestamp or zero for no expiry.
+pub fn grant_tx(space: &str, to: &str, prefix: &str, expiry: u64) -> Result<TransactionData> {
+    Ok(TransactionData {
+        typ: TransactionType::Grant,
+        space: space.to_owned(),
+        to: parse_address(to)?,
+        prefix: prefix.to_owned(),
+        expiry,
+        ..Default::default()
+    })
+}
+
+pub fn revoke_tx(space: &str, to: &str) -> Result<TransactionData> {
+    Ok(TransactionData {
+        typ: TransactionType::Revoke,
+        space: space.to_owned(),
+        to: parse_address(to)?,
+        ..Default::default()
+    })
+}
+
+/// Releases [space] so it can be claimed again.
+pub fn delete_space_tx(space: &str) -> TransactionData {
+    TransactionData {
+        typ: TransactionType::DeleteSpace,
+        space: space.to_owned(),
+        ..Default::default()
     }
 }
 
+/// Appends [value] to [key], creating the key if it is missing.
+pub fn append_tx(space: &str, key: &str, value: &str) -> TransactionData {
+    TransactionData {
+        typ: TransactionType::Append,
+        space: space.to_owned(),
+        key: key.to_owned(),
+        value: value.as_bytes().to_vec(),
+        ..Default::default()
+    }
+}
+
+/// Adds [amount] to the counter stored at [key], which starts at zero.
+pub fn increment_tx(space: &str, key: &str, amount: u64) -> TransactionData {
+    TransactionData {
+        typ: TransactionType::Increment,
+        space: space.to_owned(),
+        key: key.to_owned(),
+        amount,
+        ..Default::default()
+    }
+}
+
+/// Makes [space] owned by [threshold] of [members]. No members and a zero
+/// threshold make the sender the single owner again.
+pub fn multisig_tx(space: &str, threshold: u64, members: &[String]) -> Result<TransactionData> {
+    Ok(TransactionData {
+        typ: TransactionType::Multisig,
+        space: space.to_owned(),
+        threshold,
+        members: members
+            .iter()
+            .map(|m| parse_address(m))
+            .collect::<Result<Vec<_>>>()?,
+        ..Default::default()
+    })
+}
+
+/// Parses a hex encoded address with or without the 0x prefix.
+pub fn parse_address(address: &str) -> Result<ethereum_types::Address> {
+    ethereum_types::Address::from_str(address).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid address {}: {}", address, e),
+        )
+    })
+}
+
 /// Returns a private key from a given path or creates new.
 pub fn get_or_create_pk(path: &str) -> Result<key::secp256k1::private_key::Key> {
     if !Path::new(path).try_exists()? {
@@ -234,7 +480,11 @@ pub fn get_or_create_pk(path: &str) -> Result<key::secp256k1::private_key::Key>
         f.write_all(hex.as_bytes())?;
         return Ok(secret_key);
     }
+    read_pk(path)
+}
 
+/// Returns the private key stored at a given path.
+pub fn read_pk(path: &str) -> Result<key::secp256k1::private_key::Key> {
     let contents = std::fs::read_to_string(path)?;
     let parsed = hex::decode(contents)
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
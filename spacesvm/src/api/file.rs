@@ -0,0 +1,257 @@
+use std::io::{Error, ErrorKind, Result};
+
+use avalanche_types::key::secp256k1::private_key::Key;
+use hyper::client::HttpConnector;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{client::Client, IssueTxResponse},
+    chain::tx::{set::value_hash, tx::TransactionType, unsigned::TransactionData},
+};
+
+/// Size of the chunks a file is split into. Well below the default
+/// `max_value_size` so every chunk fits into a single set tx.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Content type of the manifest value which describes a file.
+pub const MANIFEST_CONTENT_TYPE: &str = "application/vnd.spacesvm.manifest+json";
+
+/// Stored at the path of a file and lists the keys of its chunks in order.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    /// Size of the file in bytes.
+    pub size: u64,
+
+    /// Keys of the chunks, each the value hash of the chunk.
+    pub chunks: Vec<String>,
+}
+
+/// Splits [data] into chunks of at most [chunk_size] bytes keyed by their
+/// value hash. Returns the manifest of the file and the chunks in order.
+pub fn split(data: &[u8], chunk_size: usize) -> (Manifest, Vec<(String, Vec<u8>)>) {
+    let chunks: Vec<(String, Vec<u8>)> = data
+        .chunks(chunk_size)
+        .map(|chunk| (value_hash(chunk), chunk.to_vec()))
+        .collect();
+    let manifest = Manifest {
+        size: data.len() as u64,
+        chunks: chunks.iter().map(|(key, _)| key.clone()).collect(),
+    };
+    (manifest, chunks)
+}
+
+/// Checks that [chunk] is the content of [key].
+pub fn verify_chunk(key: &str, chunk: &[u8]) -> Result<()> {
+    let hash = value_hash(chunk);
+    if hash != key {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("chunk {} has unexpected hash: {}", key, hash),
+        ));
+    }
+    Ok(())
+}
+
+impl Client<HttpConnector> {
+    /// Stores [data] as a file at [key] of [space]. The chunks are set under
+    /// their value hash, skipping the ones which are already stored, and the
+    /// manifest listing them is set at [key].
+    pub async fn set_file(
+        &self,
+        space: &str,
+        key: &str,
+        data: &[u8],
+        cosigners: &[Key],
+    ) -> Result<IssueTxResponse> {
+        let (manifest, chunks) = split(data, CHUNK_SIZE);
+        for (i, (chunk_key, chunk)) in chunks.iter().enumerate() {
+            if chunks[..i].iter().any(|(k, _)| k == chunk_key)
+                || self.resolve(space, chunk_key).await?.exists
+            {
+                log::debug!("set_file: chunk {} already stored", chunk_key);
+                continue;
+            }
+            let resp = self
+                .set_bytes(space, chunk_key, chunk.clone(), String::new(), cosigners)
+                .await?;
+            log::debug!("set_file: chunk {} tx: {:?}", chunk_key, resp.tx_id);
+        }
+
+        let value = serde_json::to_vec(&manifest)?;
+        self.set_bytes(
+            space,
+            key,
+            value,
+            MANIFEST_CONTENT_TYPE.to_owned(),
+            cosigners,
+        )
+        .await
+    }
+
+    /// Returns the file stored at [key] of [space]. Every chunk is checked
+    /// against its key and the size against the manifest.
+    pub async fn resolve_file(&self, space: &str, key: &str) -> Result<Vec<u8>> {
+        let resp = self.resolve(space, key).await?;
+        if !resp.exists {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("file not found: {}/{}", space, key),
+            ));
+        }
+        if resp.meta.content_type != MANIFEST_CONTENT_TYPE {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("not a file: {}/{}", space, key),
+            ));
+        }
+        let manifest: Manifest = serde_json::from_slice(&resp.value)?;
+
+        // the manifest comes from the chain, so its size is only trusted as
+        // far as its chunks can hold
+        let capacity = manifest
+            .size
+            .min(manifest.chunks.len().saturating_mul(CHUNK_SIZE) as u64);
+        let mut data = Vec::with_capacity(capacity as usize);
+        for chunk_key in manifest.chunks.iter() {
+            let resp = self.resolve(space, chunk_key).await?;
+            if !resp.exists {
+                return Err(Error::new(
+                    ErrorKind::NotFound,
+                    format!("chunk not found: {}", chunk_key),
+                ));
+            }
+            verify_chunk(chunk_key, &resp.value)?;
+            data.extend_from_slice(&resp.value);
+        }
+        if data.len() as u64 != manifest.size {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "file size {} does not match manifest size {}",
+                    data.len(),
+                    manifest.size
+                ),
+            ));
+        }
+        Ok(data)
+    }
+
+    async fn set_bytes(
+        &self,
+        space: &str,
+        key: &str,
+        value: Vec<u8>,
+        content_type: String,
+        cosigners: &[Key],
+    ) -> Result<IssueTxResponse> {
+        let tx_data = TransactionData {
+            typ: TransactionType::Set,
+            space: space.to_owned(),
+            key: key.to_owned(),
+            value,
+            content_type,
+            ..Default::default()
+        };
+        let resp = self.decode_tx(tx_data).await?;
+        self.issue_cosigned_tx(&resp.typed_data, cosigners).await
+    }
+}
+
+#[test]
+fn test_split() {
+    let data: Vec<u8> = (0..10u8).collect();
+    let (manifest, chunks) = split(&data, 4);
+    assert_eq!(manifest.size, 10);
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(chunks[2].1, vec![8, 9]);
+    for (key, chunk) in chunks.iter() {
+        assert!(verify_chunk(key, chunk).is_ok());
+    }
+    assert_eq!(
+        manifest.chunks,
+        chunks.iter().map(|(k, _)| k.clone()).collect::<Vec<_>>()
+    );
+    assert!(verify_chunk(&chunks[0].0, &chunks[1].1).is_err());
+
+    // equal chunks share a key
+    let (manifest, _) = split(&[7u8; 8], 4);
+    assert_eq!(manifest.chunks[0], manifest.chunks[1]);
+
+    let (manifest, chunks) = split(&[], 4);
+    assert_eq!(manifest, Manifest::default());
+    assert!(chunks.is_empty());
+}
+
+#[tokio::test]
+async fn test_upload_new_file() {
+    use avalanche_types::subnet::rpc::database::memdb;
+
+    use crate::chain::{
+        storage,
+        tx::{base, claim, set, unsigned},
+    };
+
+    let db = memdb::Database::new();
+    let ctx = |tx_id: u8| {
+        unsigned::TransactionContext::for_test(
+            db.clone(),
+            ethereum_types::Address::zero(),
+            1,
+            tx_id,
+        )
+    };
+    let set = |key: &str, value: &[u8], content_type: &str, tx_id: u8| {
+        let tx = set::Tx {
+            base_tx: base::Tx::default(),
+            space: "kvs".to_string(),
+            key: key.to_string(),
+            value: value.to_vec(),
+            expiry: 0,
+            content_type: content_type.to_string(),
+            attributes: Default::default(),
+        };
+        let ctx = ctx(tx_id);
+        async move { unsigned::Transaction::execute(&tx, ctx).await.unwrap() }
+    };
+    let resolve = |key: String| {
+        let db = db.clone();
+        async move {
+            storage::get_value(&db, "kvs".as_bytes(), key.as_bytes(), 1)
+                .await
+                .unwrap()
+        }
+    };
+
+    let tx = claim::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+    };
+    unsigned::Transaction::execute(&tx, ctx(1)).await.unwrap();
+
+    // chunks of a new file are not stored yet and resolve as missing
+    let data: Vec<u8> = (0..10u8).collect();
+    let (manifest, chunks) = split(&data, 4);
+    for (i, (chunk_key, chunk)) in chunks.iter().enumerate() {
+        assert!(resolve(chunk_key.clone()).await.is_none());
+        set(chunk_key, chunk, "", 2 + i as u8).await;
+    }
+    let value = serde_json::to_vec(&manifest).unwrap();
+    set("file", &value, MANIFEST_CONTENT_TYPE, 9).await;
+
+    // the file is read back through its manifest
+    let vmeta = storage::get_value_meta(&db, "kvs".as_bytes(), "file".as_bytes(), 1)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(vmeta.content_type, MANIFEST_CONTENT_TYPE);
+    let stored: Manifest =
+        serde_json::from_slice(&resolve("file".to_string()).await.unwrap()).unwrap();
+    assert_eq!(stored, manifest);
+    let mut read = Vec::new();
+    for chunk_key in stored.chunks.iter() {
+        let chunk = resolve(chunk_key.clone()).await.unwrap();
+        verify_chunk(chunk_key, &chunk).unwrap();
+        read.extend_from_slice(&chunk);
+    }
+    assert_eq!(read, data);
+}
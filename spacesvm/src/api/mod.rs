@@ -1,4 +1,5 @@
 pub mod client;
+pub mod file;
 pub mod service;
 
 use avalanche_types::ids;
@@ -25,6 +26,12 @@ pub trait Service {
 
     #[rpc(name = "resolve", alias("spacesvm.resolve"))]
     fn resolve(&self, params: ResolveArgs) -> BoxFuture<Result<ResolveResponse>>;
+
+    #[rpc(name = "balance", alias("spacesvm.balance"))]
+    fn balance(&self, params: BalanceArgs) -> BoxFuture<Result<BalanceResponse>>;
+
+    #[rpc(name = "info", alias("spacesvm.info"))]
+    fn info(&self, params: InfoArgs) -> BoxFuture<Result<InfoResponse>>;
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -47,6 +54,10 @@ pub struct IssueRawTxResponse {
 pub struct IssueTxArgs {
     pub typed_data: TypedData,
     pub signature: Vec<u8>,
+    /// Signatures of the cosigners declared by the typed data over the same
+    /// typed data, in the same order.
+    #[serde(default)]
+    pub cosignatures: Vec<Vec<u8>>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -58,6 +69,9 @@ pub struct IssueTxResponse {
 #[derive(Deserialize, Serialize, Debug)]
 pub struct DecodeTxArgs {
     pub tx_data: tx::unsigned::TransactionData,
+    /// Members of a multisig which cosign the tx, added to the typed data.
+    #[serde(default)]
+    pub cosigners: Vec<ethereum_types::Address>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -78,6 +92,29 @@ pub struct ResolveResponse {
     pub meta: ValueMeta,
 }
 
+#[derive(Deserialize, Serialize, Debug)]
+pub struct BalanceArgs {
+    pub address: ethereum_types::Address,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct BalanceResponse {
+    pub balance: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct InfoArgs {
+    pub space: Vec<u8>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct InfoResponse {
+    /// None if the space does not exist or has expired.
+    pub info: Option<tx::claim::Info>,
+    /// Limits the usage of the space must stay within.
+    pub quota: tx::claim::Usage,
+}
+
 pub fn create_jsonrpc_error(e: std::io::Error) -> Error {
     let mut error = Error::new(ErrorCode::InternalError);
     error.message = format!("{}", e);
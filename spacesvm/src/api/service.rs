@@ -6,6 +6,7 @@ use crate::{
     vm::inner::Inner,
 };
 
+use chrono::Utc;
 use tokio::sync::RwLock;
 
 pub struct Service {
@@ -40,6 +41,11 @@ impl crate::api::Service for Service {
                 .map_err(create_jsonrpc_error)?;
 
             let mut tx = chain::tx::tx::Transaction::new(unsigned_tx, params.signature);
+            tx.cosigners = params
+                .typed_data
+                .parse_cosigners()
+                .map_err(create_jsonrpc_error)?;
+            tx.cosignatures = params.cosignatures;
             tx.init().await.map_err(create_jsonrpc_error)?;
             let tx_id = tx.id().await;
 
@@ -75,6 +81,9 @@ impl crate::api::Service for Service {
         Box::pin(async move {
             let mut utx = params.tx_data.decode().map_err(create_jsonrpc_error)?;
             let inner = vm.write().await;
+            utx.validate(&inner.genesis)
+                .await
+                .map_err(create_jsonrpc_error)?;
             let last_accepted = &inner
                 .state
                 .get_last_accepted()
@@ -82,7 +91,15 @@ impl crate::api::Service for Service {
                 .map_err(create_jsonrpc_error)?;
 
             utx.set_block_id(*last_accepted).await;
-            let typed_data = utx.typed_data().await;
+
+            // txs are priced at the minimum and signed for this chain only
+            let mut base_tx = utx.get_base_tx().await;
+            base_tx.price = inner.genesis.min_price;
+            base_tx.magic = inner.genesis.magic;
+            base_tx.chain_id = inner.state.get_chain_id().await;
+            utx.set_base_tx(base_tx).await;
+
+            let typed_data = utx.typed_data().await.with_cosigners(&params.cosigners);
 
             let string = serde_json::to_string(&typed_data).unwrap();
 
@@ -99,14 +116,25 @@ impl crate::api::Service for Service {
         Box::pin(async move {
             let inner = vm.read().await;
             let db = inner.state.get_db().await;
-            let value = chain::storage::get_value(&db, &params.space, &params.key)
+
+            // keys of an expired space are no longer resolvable
+            let now = Utc::now().timestamp() as u64;
+            let info = chain::storage::get_space_info(&db, &params.space)
+                .await
+                .map_err(create_jsonrpc_error)?;
+            match info {
+                Some(info) if !info.is_expired(now) => {}
+                _ => return Ok(ResolveResponse::default()),
+            }
+
+            let value = chain::storage::get_value(&db, &params.space, &params.key, now)
                 .await
                 .map_err(create_jsonrpc_error)?;
             if value.is_none() {
                 return Ok(ResolveResponse::default());
             }
 
-            let meta = chain::storage::get_value_meta(&db, &params.space, &params.key)
+            let meta = chain::storage::get_value_meta(&db, &params.space, &params.key, now)
                 .await
                 .map_err(create_jsonrpc_error)?;
             if meta.is_none() {
@@ -120,4 +148,41 @@ impl crate::api::Service for Service {
             })
         })
     }
+
+    fn balance(&self, params: BalanceArgs) -> BoxFuture<Result<BalanceResponse>> {
+        log::debug!("balance: called");
+        let vm = Arc::clone(&self.vm_inner);
+
+        Box::pin(async move {
+            let inner = vm.read().await;
+            let db = inner.state.get_db().await;
+            let balance = chain::storage::get_balance(&db, &params.address)
+                .await
+                .map_err(create_jsonrpc_error)?;
+
+            Ok(BalanceResponse { balance })
+        })
+    }
+
+    fn info(&self, params: InfoArgs) -> BoxFuture<Result<InfoResponse>> {
+        log::debug!("info: called");
+        let vm = Arc::clone(&self.vm_inner);
+
+        Box::pin(async move {
+            let inner = vm.read().await;
+            let db = inner.state.get_db().await;
+            let quota = chain::tx::claim::Usage {
+                keys: inner.genesis.max_space_keys,
+                bytes: inner.genesis.max_space_bytes,
+                stored: inner.genesis.max_space_stored_bytes,
+            };
+
+            let info = chain::storage::get_space_info(&db, &params.space)
+                .await
+                .map_err(create_jsonrpc_error)?
+                .filter(|info| !info.is_expired(Utc::now().timestamp() as u64));
+
+            Ok(InfoResponse { info, quota })
+        })
+    }
 }
@@ -86,6 +86,7 @@ pub fn execute_genesis(author: &str, msg: &str, p: &str) -> Result<()> {
     let g = genesis::Genesis {
         author: String::from(author),
         welcome_message: String::from(msg),
+        ..Default::default()
     };
     g.sync(p)
 }
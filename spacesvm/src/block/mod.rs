@@ -1,5 +1,6 @@
 pub mod builder;
 pub mod state;
+pub mod sweeper;
 
 use std::io::{Error, ErrorKind, Result};
 use std::vec;
@@ -8,44 +9,39 @@ use avalanche_types::subnet::rpc::consensus::snowman::StatusWriter;
 use avalanche_types::{
     choices::{self, status::Status},
     hash, ids,
+    subnet::{self, rpc::database::versiondb},
 };
 use derivative::{self, Derivative};
-use serde::{Deserialize, Serialize};
 
 use crate::chain::{self, tx::Transaction};
 
 pub const DATA_LEN: usize = 32;
 pub const BLOCKS_LRU_SIZE: usize = 8192;
+/// Maximum number of expired keys removed by a single block.
+pub const EXPIRED_KEYS_PER_BLOCK: usize = 128;
 
-#[derive(Serialize, Deserialize, Clone, Derivative)]
+#[derive(Clone, Derivative)]
 #[derivative(Debug, Default)]
 pub struct Block {
-    #[serde(deserialize_with = "ids::must_deserialize_id")]
     pub parent: ids::Id,
     pub height: u64,
     pub timestamp: u64,
     pub data: Vec<u8>,
 
-    #[serde(skip)]
     // current block status.
     pub st: Status,
 
-    #[serde(skip)]
     // encoded block bytes.
     pub bytes: Vec<u8>,
 
-    #[serde(skip)]
     // generated block Id.
     pub id: ids::Id,
 
     #[derivative(Debug = "ignore")]
-    #[serde(skip)]
     pub state: state::State,
 
-    #[serde(skip)]
     pub txs: Vec<chain::tx::tx::Transaction>,
 
-    #[serde(skip)]
     pub children: Vec<Block>,
 }
 
@@ -90,6 +86,89 @@ impl Block {
             children: vec![],
         }
     }
+
+    /// Removes the expired keys from [db] and returns the history of the
+    /// ancestors of the block, which the txs of the block are executed on.
+    async fn prepare_txs(
+        &self,
+        db: &Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    ) -> Result<chain::vm::Context> {
+        let window = self.state.get_genesis().await.lookback_window;
+        let context = self.state.recent_context(self.parent, window).await?;
+
+        // expired keys are removed as part of the block as they release quota
+        // of live spaces, so every node frees the same storage before the
+        // quotas of its txs are checked. Expired spaces are left to the
+        // sweeper.
+        let pruned = chain::storage::prune_expired_keys(
+            &mut db.clone(),
+            self.timestamp,
+            EXPIRED_KEYS_PER_BLOCK,
+        )
+        .await?;
+        if pruned > 0 {
+            log::debug!("prepare_txs: removed {} expired keys", pruned);
+        }
+
+        Ok(context)
+    }
+
+    /// Executes the txs of the block against [db] using the history of its
+    /// ancestors.
+    pub async fn execute_txs(
+        &mut self,
+        db: &Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    ) -> Result<()> {
+        let mut context = self.prepare_txs(db).await?;
+
+        let block = self.clone();
+        for tx in self.txs.iter_mut() {
+            tx.init().await?;
+            tx.execute(db, &block, &context).await?;
+            context.recent_tx_ids.insert(tx.id);
+        }
+
+        Ok(())
+    }
+
+    /// Executes [candidates] in order on top of the state of the parent and
+    /// keeps the ones which succeed as the txs of the block. Returns the
+    /// candidates which failed, for example because an earlier tx spent the
+    /// same balance or their block id is no longer recent.
+    pub async fn select_txs(
+        &mut self,
+        candidates: Vec<chain::tx::tx::Transaction>,
+    ) -> Result<Vec<chain::tx::tx::Transaction>> {
+        let overlay = self.state.new_overlay(self.parent).await;
+        let db: Box<dyn subnet::rpc::database::Database + Send + Sync> = Box::new(overlay);
+        let mut context = self.prepare_txs(&db).await?;
+
+        let block = self.clone();
+        let mut failed = Vec::new();
+        for mut tx in candidates.into_iter() {
+            // the writes of a failed tx are dropped with its own overlay
+            let tx_overlay = versiondb::Database::new(db.clone());
+            let tx_db: Box<dyn subnet::rpc::database::Database + Send + Sync> =
+                Box::new(tx_overlay.clone());
+            let resp = match tx.init().await {
+                Ok(_) => tx.execute(&tx_db, &block, &context).await,
+                Err(e) => Err(e),
+            };
+            match resp {
+                Ok(_) => {
+                    tx_overlay.commit().await?;
+                    context.recent_tx_ids.insert(tx.id);
+                    self.txs.push(tx);
+                }
+                Err(e) => {
+                    log::debug!("select_txs: dropping tx {}: {}", tx.id, e);
+                    failed.push(tx);
+                }
+            }
+        }
+
+        Ok(failed)
+    }
 }
 
 #[tonic::async_trait]
@@ -101,14 +180,12 @@ impl avalanche_types::subnet::rpc::consensus::snowman::Block for Block {
 
     /// Helper method which serializes the block to bytes.
     async fn to_bytes(&self) -> Result<Vec<u8>> {
-        let block = self.clone();
-        let bytes = serde_json::to_vec(&block).map_err(|e| {
+        chain::codec::encode_block(self).await.map_err(|e| {
             Error::new(
                 ErrorKind::InvalidData,
                 format!("failed to serialize block to bytes: {:?}", e),
             )
-        })?;
-        return Ok(bytes);
+        })
     }
 
     /// Implements "snowman.Block"
@@ -130,12 +207,15 @@ impl avalanche_types::subnet::rpc::consensus::snowman::Block for Block {
     async fn verify(&mut self) -> Result<()> {
         let parent_id = self.parent().await;
 
-        let mut parent_block = self.state.get_block(parent_id).await.map_err(|e| {
-            Error::new(
-                ErrorKind::Other,
-                format!("failed to verify parent block not found: {}", e.to_string()),
-            )
-        })?;
+        let mut parent_block = match self.state.get_verified_block(parent_id).await {
+            Some(block) => block,
+            None => self.state.get_block(parent_id).await.map_err(|e| {
+                Error::new(
+                    ErrorKind::Other,
+                    format!("failed to verify parent block not found: {}", e.to_string()),
+                )
+            })?,
+        };
 
         // ensure block height comes right after its parent's height
         let height = self.height().await;
@@ -159,13 +239,17 @@ impl avalanche_types::subnet::rpc::consensus::snowman::Block for Block {
             ));
         }
 
-        let state = self.state.clone();
-        state.set_last_accepted(&mut self).await.map_err(|e| {
+        // execute on top of the parent state without touching the database
+        let overlay = self.state.new_overlay(parent_id).await;
+        let db: Box<dyn subnet::rpc::database::Database + Send + Sync> = Box::new(overlay.clone());
+        self.execute_txs(&db).await.map_err(|e| {
             Error::new(
-                ErrorKind::Other,
-                format!("set last accepted failed: {}", e.to_string()),
+                ErrorKind::InvalidData,
+                format!("failed to execute block txs: {}", e.to_string()),
             )
         })?;
+        self.state.set_verified_db(self.id, overlay).await;
+        self.state.set_verified_block(self.to_owned()).await?;
 
         parent_block.children.push(self.to_owned());
 
@@ -191,14 +275,13 @@ impl avalanche_types::subnet::rpc::consensus::snowman::Decidable for Block {
         self.set_status(Status::Accepted).await;
 
         let block_id = self.id().await;
-        let mut block = self.clone();
-        let db = self.state.get_db().await;
-
-        for tx in self.txs.iter_mut() {
-            tx.init().await?;
-            tx.execute(&db, &block).await?;
+        if self.state.get_verified_block(block_id).await.is_none() {
+            // the block was never verified by this node
+            avalanche_types::subnet::rpc::consensus::snowman::Block::verify(self).await?;
         }
 
+        let mut block = self.clone();
+
         // add block to cache
         self.state
             .set_accepted_block(block_id, &block)
@@ -210,6 +293,7 @@ impl avalanche_types::subnet::rpc::consensus::snowman::Decidable for Block {
                 )
             })?;
 
+        // persist the block together with the state changes made during verification
         self.state
             .set_last_accepted(&mut block)
             .await
@@ -227,6 +311,7 @@ impl avalanche_types::subnet::rpc::consensus::snowman::Decidable for Block {
 
         // remove this block from verified blocks as it's rejected.
         let _ = self.state.remove_verified_block(self.id).await;
+        self.state.abort_verified_db(self.id).await?;
 
         Ok(())
     }
@@ -251,3 +336,80 @@ impl avalanche_types::subnet::rpc::consensus::snowman::StatusWriter for Block {
         self.st = status;
     }
 }
+
+#[tokio::test]
+async fn test_select_txs() {
+    use avalanche_types::{
+        key::secp256k1::private_key::Key,
+        subnet::rpc::consensus::snowman::{Block as _, Initializer as _},
+    };
+
+    use crate::chain::tx::{base, decoder, tx::TransactionType, unsigned::TransactionData};
+
+    async fn transfer(key: &Key, to: u64, block_id: ids::Id) -> chain::tx::tx::Transaction {
+        let mut utx = TransactionData {
+            typ: TransactionType::Transfer,
+            to: ethereum_types::Address::from_low_u64_be(to),
+            units: 6,
+            ..Default::default()
+        }
+        .decode()
+        .unwrap();
+        utx.set_base_tx(base::Tx {
+            block_id,
+            price: 1,
+            magic: 1,
+            chain_id: ids::Id::empty(),
+        })
+        .await;
+        let dh = decoder::hash_structured_data(&utx.typed_data().await).unwrap();
+        let sig = key.sign_digest(dh.as_bytes()).unwrap().to_bytes().to_vec();
+        let mut tx = chain::tx::tx::Transaction::new(utx, sig);
+        tx.init().await.unwrap();
+        tx
+    }
+
+    let state = state::State::default();
+    let mut parent = Block::new(ids::Id::empty(), 0, &[], 0, state.clone());
+    parent.id = ids::Id::from_slice(&[1u8; ids::LEN]);
+    state.set_last_accepted(&mut parent).await.unwrap();
+
+    let key = Key::generate().unwrap();
+    let sender = key.to_public_key().to_h160();
+    chain::storage::add_balance(&mut state.get_db().await, &sender, 10)
+        .await
+        .unwrap();
+
+    // the balance only covers one of the spends, and the last tx references
+    // a block outside of the lookback window
+    let spend = transfer(&key, 2, parent.id).await;
+    let double_spend = transfer(&key, 3, parent.id).await;
+    let stale = transfer(&key, 4, ids::Id::from_slice(&[9u8; ids::LEN])).await;
+
+    let mut block = Block::new(parent.id, 1, &[], 1, state.clone());
+    let failed = block
+        .select_txs(vec![spend.clone(), double_spend.clone(), stale.clone()])
+        .await
+        .unwrap();
+    assert_eq!(
+        block.txs.iter().map(|tx| tx.id).collect::<Vec<_>>(),
+        vec![spend.id]
+    );
+    assert_eq!(
+        failed.iter().map(|tx| tx.id).collect::<Vec<_>>(),
+        vec![double_spend.id, stale.id]
+    );
+
+    // selecting does not write to the database
+    assert_eq!(
+        chain::storage::get_balance(&state.get_db().await, &sender)
+            .await
+            .unwrap(),
+        10
+    );
+
+    // the block of the selected txs verifies
+    let bytes = block.to_bytes().await.unwrap();
+    block.init(&bytes, Status::Processing).await.unwrap();
+    block.verify().await.unwrap();
+}
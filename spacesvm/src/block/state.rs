@@ -8,16 +8,22 @@ use std::{
 use avalanche_types::{
     choices::status::{self, Status},
     hash, ids,
-    subnet::{self, rpc::database::errors},
+    subnet::{
+        self,
+        rpc::database::{errors, versiondb},
+    },
 };
 use lru::LruCache;
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
-
-use crate::chain::{
-    self,
-    storage::{prefix_block_key, prefix_tx_value_key},
-    tx::Transaction,
+use tokio::sync::{broadcast, RwLock};
+
+use crate::{
+    chain::{
+        self,
+        storage::{self, prefix_block_key, prefix_tx_value_key},
+        tx::Transaction,
+    },
+    genesis::Genesis,
 };
 
 use super::{Block, BLOCKS_LRU_SIZE};
@@ -25,6 +31,7 @@ use super::{Block, BLOCKS_LRU_SIZE};
 const LAST_ACCEPTED_BLOCK_KEY: &[u8] = b"last_accepted";
 pub const BYTE_DELIMITER: &[u8] = b"/";
 pub const HASH_LEN: usize = ids::LEN + 2;
+const ACCEPTED_CHANNEL_SIZE: usize = 16;
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct ValueMeta {
@@ -56,31 +63,51 @@ impl Default for StateInner {
     fn default() -> StateInner {
         StateInner {
             db: subnet::rpc::database::memdb::Database::new(),
+            genesis: Genesis::default(),
+            chain_id: ids::Id::empty(),
             last_accepted: ids::Id::empty(),
             verified_blocks: HashMap::new(),
+            verified_dbs: HashMap::new(),
             accepted_blocks: LruCache::new(NonZeroUsize::new(BLOCKS_LRU_SIZE).unwrap()),
+            accepted_tx: broadcast::channel(ACCEPTED_CHANNEL_SIZE).0,
         }
     }
 }
 
 pub struct StateInner {
     db: Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    /// Genesis of the chain, used as parameters during tx execution
+    genesis: Genesis,
+    /// Id of the chain, signed into every transaction
+    chain_id: ids::Id,
     /// The last accepted block by this VM
     last_accepted: ids::Id,
     /// Blocks that have been verified but not yet accepted
     verified_blocks: HashMap<ids::Id, Block>,
+    /// State changes of verified blocks which are persisted once accepted
+    verified_dbs: HashMap<ids::Id, versiondb::Database>,
     /// Cache of accepted blocks
     accepted_blocks: LruCache<ids::Id, Block>,
+    /// Notifies subscribers of the timestamp of every accepted block
+    accepted_tx: broadcast::Sender<u64>,
 }
 
 impl State {
-    pub fn new(db: Box<dyn subnet::rpc::database::Database + Send + Sync>) -> Self {
+    pub fn new(
+        db: Box<dyn subnet::rpc::database::Database + Send + Sync>,
+        genesis: Genesis,
+        chain_id: ids::Id,
+    ) -> Self {
         return Self {
             inner: Arc::new(RwLock::new(StateInner {
                 db,
+                genesis,
+                chain_id,
                 verified_blocks: HashMap::new(),
+                verified_dbs: HashMap::new(),
                 last_accepted: ids::Id::empty(),
                 accepted_blocks: LruCache::new(NonZeroUsize::new(BLOCKS_LRU_SIZE).unwrap()),
+                accepted_tx: broadcast::channel(ACCEPTED_CHANNEL_SIZE).0,
             })),
         };
     }
@@ -131,17 +158,56 @@ impl State {
         Ok(inner.verified_blocks.remove(&id))
     }
 
-    /// Persists last accepted block Id into database.
+    /// Returns an overlay on top of the state of the block [parent]. Writes to
+    /// the overlay are kept in memory until it is committed.
+    pub async fn new_overlay(&self, parent: ids::Id) -> versiondb::Database {
+        let inner = self.inner.read().await;
+
+        let db: Box<dyn subnet::rpc::database::Database + Send + Sync> =
+            match inner.verified_dbs.get(&parent) {
+                Some(parent_db) => Box::new(parent_db.clone()),
+                None => inner.db.clone(),
+            };
+        versiondb::Database::new(db)
+    }
+
+    /// Stores the overlay holding the state changes of a verified block.
+    pub async fn set_verified_db(&self, id: ids::Id, db: versiondb::Database) {
+        let mut inner = self.inner.write().await;
+        inner.verified_dbs.insert(id, db);
+    }
+
+    /// Discards the state changes of a rejected block.
+    pub async fn abort_verified_db(&self, id: ids::Id) -> Result<()> {
+        let mut inner = self.inner.write().await;
+
+        if let Some(mut db) = inner.verified_dbs.remove(&id) {
+            db.abort().await?;
+        }
+        Ok(())
+    }
+
+    /// Persists the block together with the state changes made while it was
+    /// verified and the last accepted block Id. All writes are committed to the
+    /// database as a single batch so an interrupted accept leaves no trace and
+    /// the last accepted block always refers to a fully stored block, without
+    /// any repair on startup.
     pub async fn set_last_accepted(&self, block: &mut Block) -> Result<()> {
         let block_id = block.id;
 
-        // persist last_accepted Id to database with fixed key
         let mut inner = self.inner.write().await;
 
+        // the parent has been committed already so write straight to disk
+        let mut vdb = match inner.verified_dbs.remove(&block_id) {
+            Some(vdb) => vdb,
+            None => versiondb::Database::new(inner.db.clone()),
+        };
+        vdb.set_database(inner.db.clone()).await?;
+        let mut db: Box<dyn subnet::rpc::database::Database + Send + Sync> = Box::new(vdb.clone());
+
+        // persist last_accepted Id to database with fixed key
         log::debug!("set_last_accepted: key value: {:?}", block_id.to_vec());
-        inner
-            .db
-            .put(LAST_ACCEPTED_BLOCK_KEY, &block_id.to_vec())
+        db.put(LAST_ACCEPTED_BLOCK_KEY, &block_id.to_vec())
             .await
             .map_err(|e| {
                 Error::new(
@@ -150,43 +216,54 @@ impl State {
                 )
             })?;
 
+        // the values of set txs were stored when they were executed, the
+        // block keeps their tx ids in their place
         for tx in block.txs.iter_mut() {
-            if is_set_tx(&tx).await {
-                let maybe_value = &tx.unsigned_transaction.get_value().await;
-                if maybe_value.is_none() {
-                    continue;
-                }
-                let value = maybe_value.as_ref().unwrap();
-                log::debug!(
-                    "set_last_accepted: put prefix_tx_value_key: {:?}\n",
-                    prefix_tx_value_key(&tx.id)
-                );
-                log::debug!(
-                    "set_last_accepted: put prefix_tx_value_key value: {:?}\n",
-                    value
-                );
-
-                inner
-                    .db
-                    .put(&prefix_tx_value_key(&tx.id), &value)
-                    .await
-                    .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
-
+            if is_set_tx(&tx).await && tx.unsigned_transaction.get_value().await.is_some() {
                 tx.unsigned_transaction.set_value(tx.id.to_vec()).await? // used to properly parse on restore
             }
         }
 
-        let bytes = &serde_json::to_vec(&block)?;
+        let bytes = &chain::codec::encode_block(block).await?;
 
-        inner
-            .db
-            .put(&prefix_block_key(&block_id), &bytes)
+        db.put(&prefix_block_key(&block_id), &bytes)
             .await
             .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
 
+        let batch = vdb.commit_batch().await?;
+        batch.write().await.map_err(|e| {
+            Error::new(
+                ErrorKind::Other,
+                format!("failed to write accepted block {}: {}", block_id, e),
+            )
+        })?;
+        vdb.abort().await?;
+
+        inner.last_accepted = block_id;
+
+        // no subscribers is not an error
+        let _ = inner.accepted_tx.send(block.timestamp);
         Ok(())
     }
 
+    /// Returns a broadcast receiver for the timestamps of accepted blocks.
+    pub async fn subscribe_accepted(&self) -> broadcast::Receiver<u64> {
+        let inner = self.inner.read().await;
+        inner.accepted_tx.subscribe()
+    }
+
+    /// Removes up to [limit] keys, values and grants of spaces which expired
+    /// before [now] from the accepted state. Txs treat expired spaces as
+    /// missing, so blocks execute the same whether or not they were removed.
+    /// The lock is held so accepting a block, which may claim the space
+    /// again, never interleaves with the removal.
+    pub async fn prune_expired(&self, now: u64, limit: usize) -> Result<usize> {
+        let inner = self.inner.write().await;
+
+        let mut db = inner.db.clone();
+        storage::prune_expired(&mut db, now, limit).await
+    }
+
     /// Attempts to retrieve the last accepted block and return the corresponding
     /// block Id. If not the key is found returns Id::empty().
     pub async fn get_last_accepted(&self) -> Result<ids::Id> {
@@ -210,7 +287,9 @@ impl State {
         }
     }
 
-    /// Attempts to return block on disk state.
+    /// Attempts to return block on disk state. Blocks of which a set value was
+    /// deleted since keep their id and the ids of their txs, but not the
+    /// bytes they were accepted with, see [storage].
     pub async fn get_block(&self, block_id: ids::Id) -> Result<Block> {
         log::debug!("get_block: called");
         let mut inner = self.inner.write().await;
@@ -221,24 +300,35 @@ impl State {
         }
 
         let block_bytes = inner.db.get(&prefix_block_key(&block_id)).await?;
-        let mut block: Block = serde_json::from_slice(&block_bytes)?;
+        // the chain id is read from the held lock, the state's own getters
+        // would wait on it forever
+        let mut block =
+            chain::codec::decode_block(&block_bytes, inner.chain_id, self.clone()).await?;
 
         //  restore the unlinked values associated with all set_tx.value
-        for tx in block.txs.iter_mut() {
+        let mut pruned_txs = Vec::new();
+        for (i, tx) in block.txs.iter_mut().enumerate() {
             if is_set_tx(&tx).await {
                 if let Some(tx_value) = tx.unsigned_transaction.get_value().await {
                     let tx_id = &ids::Id::from_slice(&tx_value);
 
-                    let value = inner
-                        .db
-                        .get(&prefix_tx_value_key(tx_id))
-                        .await
-                        .map_err(|e| {
-                            Error::new(
+                    let value = match inner.db.get(&prefix_tx_value_key(tx_id)).await {
+                        Ok(value) => value,
+                        Err(e) => {
+                            // the key of the value was overwritten, deleted
+                            // or expired since
+                            if errors::is_not_found(&e) {
+                                log::debug!("get_block: value pruned for tx: {}", tx_id);
+                                tx.id = *tx_id;
+                                pruned_txs.push(i);
+                                continue;
+                            }
+                            return Err(Error::new(
                                 ErrorKind::Other,
                                 format!("failed to get tx value key: {}", e),
-                            )
-                        })?;
+                            ));
+                        }
+                    };
                     tx.unsigned_transaction.set_value(value).await?
                 } else {
                     continue;
@@ -247,14 +337,22 @@ impl State {
         }
 
         // parse block inline
-        let bytes = &serde_json::to_vec(&block)?;
+        let bytes = &chain::codec::encode_block(&block).await?;
         block.bytes = bytes.to_vec();
-        block.id = ids::Id::from_slice(hash::keccak256(bytes).as_bytes());
+        if pruned_txs.is_empty() {
+            block.id = ids::Id::from_slice(hash::keccak256(bytes).as_bytes());
+        } else {
+            // the accepted bytes can no longer be restored, the block keeps
+            // its id and the ids of its txs so it still counts as history
+            block.id = block_id;
+        }
         block.st = status::Status::Accepted;
         block.state = self.clone();
 
-        for tx in block.txs.iter_mut() {
-            tx.init().await?;
+        for (i, tx) in block.txs.iter_mut().enumerate() {
+            if !pruned_txs.contains(&i) {
+                tx.init().await?;
+            }
         }
 
         log::debug!("get_block: {:?}", block);
@@ -262,7 +360,7 @@ impl State {
     }
 
     /// Attempts to parse a byte array into a block. If the source is empty
-    /// bytes will be marshalled from a default block.
+    /// bytes will be encoded from the given block or a default block.
     pub async fn parse_block(
         &self,
         maybe_block: Option<Block>,
@@ -270,14 +368,12 @@ impl State {
         status: Status,
     ) -> Result<Block> {
         let mut block: Block;
-        if maybe_block.is_none() {
-            block = Block::default();
-        } else {
-            block = maybe_block.unwrap();
-        }
-
         if source.is_empty() {
-            source = serde_json::to_vec(&block)?;
+            block = maybe_block.unwrap_or_default();
+            source = chain::codec::encode_block(&block).await?;
+        } else {
+            let chain_id = self.get_chain_id().await;
+            block = chain::codec::decode_block(&source, chain_id, self.clone()).await?;
         }
         block.bytes = source.to_vec();
         block.id = ids::Id::from_slice(hash::keccak256(source).as_bytes());
@@ -311,8 +407,149 @@ impl State {
         let inner = self.inner.read().await;
         inner.db.clone()
     }
+
+    /// Returns the ids of the last [window] blocks ending with [block_id] and
+    /// of the txs included in them.
+    pub async fn recent_context(
+        &self,
+        block_id: ids::Id,
+        window: u64,
+    ) -> Result<chain::vm::Context> {
+        let mut context = chain::vm::Context::default();
+
+        let mut id = block_id;
+        for _ in 0..window {
+            let block = match self.get_verified_block(id).await {
+                Some(block) => block,
+                None => self.get_block(id).await?,
+            };
+            context.recent_block_ids.insert(id);
+            for tx in block.txs.iter() {
+                context.recent_tx_ids.insert(tx.id);
+            }
+
+            if block.height == 0 {
+                break;
+            }
+            id = block.parent;
+        }
+
+        Ok(context)
+    }
+
+    pub async fn get_genesis(&self) -> Genesis {
+        let inner = self.inner.read().await;
+        inner.genesis.clone()
+    }
+
+    pub async fn get_chain_id(&self) -> ids::Id {
+        let inner = self.inner.read().await;
+        inner.chain_id
+    }
 }
 
 async fn is_set_tx(tx: &chain::tx::tx::Transaction) -> bool {
     tx.unsigned_transaction.typ().await == chain::tx::tx::TransactionType::Set
 }
+
+#[tokio::test]
+async fn test_verified_db() {
+    let state = State::default();
+    let base = state.get_db().await;
+
+    // writes of a verified block stay in its overlay
+    let block_id = ids::Id::from_slice(&[1u8; ids::LEN]);
+    let overlay = state.new_overlay(ids::Id::empty()).await;
+    let mut db: Box<dyn subnet::rpc::database::Database + Send + Sync> = Box::new(overlay.clone());
+    db.put(b"foo", b"bar").await.unwrap();
+    state.set_verified_db(block_id, overlay).await;
+    assert!(!base.has(b"foo").await.unwrap());
+
+    // children are executed on top of their parent
+    let child_id = ids::Id::from_slice(&[2u8; ids::LEN]);
+    let child = state.new_overlay(block_id).await;
+    let mut db: Box<dyn subnet::rpc::database::Database + Send + Sync> = Box::new(child.clone());
+    assert_eq!(db.get(b"foo").await.unwrap(), b"bar".to_vec());
+    db.put(b"baz", b"qux").await.unwrap();
+    state.set_verified_db(child_id, child).await;
+
+    // accepting the parent persists only its own writes along with the block
+    let mut block = Block::new(ids::Id::empty(), 1, &[], 0, state.clone());
+    block.id = block_id;
+    state.set_last_accepted(&mut block).await.unwrap();
+    assert!(base.has(b"foo").await.unwrap());
+    assert!(!base.has(b"baz").await.unwrap());
+    assert!(base.has(&prefix_block_key(&block_id)).await.unwrap());
+    assert_eq!(state.get_last_accepted().await.unwrap(), block_id);
+
+    // rejecting the child discards its writes
+    state.abort_verified_db(child_id).await.unwrap();
+    assert!(!base.has(b"baz").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_get_block_from_db() {
+    use crate::chain::tx::{tx::TransactionType, unsigned::TransactionData, Transaction as _};
+    use avalanche_types::key;
+
+    let state = State::default();
+
+    let utx = TransactionData {
+        typ: TransactionType::Set,
+        space: "kvs".to_string(),
+        key: "foo".to_string(),
+        value: "bar".as_bytes().to_vec(),
+        ..Default::default()
+    }
+    .decode()
+    .unwrap();
+    let dh = chain::tx::decoder::hash_structured_data(&utx.typed_data().await).unwrap();
+    let sender_key = key::secp256k1::private_key::Key::generate().unwrap();
+    let sig = sender_key
+        .sign_digest(dh.as_bytes())
+        .unwrap()
+        .to_bytes()
+        .to_vec();
+    let mut tx = chain::tx::tx::Transaction::new(utx, sig);
+    tx.init().await.unwrap();
+    let tx_id = tx.id;
+
+    // the value is stored when the tx is executed
+    let mut db = state.get_db().await;
+    db.put(&prefix_tx_value_key(&tx_id), "bar".as_bytes())
+        .await
+        .unwrap();
+
+    let block_id = ids::Id::from_slice(&[1u8; ids::LEN]);
+    let mut block = Block::new(ids::Id::empty(), 1, &[], 0, state.clone());
+    block.id = block_id;
+    block.txs.push(tx);
+    state.set_last_accepted(&mut block).await.unwrap();
+
+    // blocks which are no longer cached are decoded from the database
+    state.inner.write().await.accepted_blocks.clear();
+    let block = state.get_block(block_id).await.unwrap();
+    assert_eq!(block.height, 1);
+    assert_eq!(block.txs.len(), 1);
+    assert_eq!(block.txs[0].id, tx_id);
+    assert_eq!(block.txs[0].sender, sender_key.to_public_key().to_h160());
+    assert_eq!(
+        block.txs[0].unsigned_transaction.get_value().await,
+        Some("bar".as_bytes().to_vec())
+    );
+    assert_eq!(
+        block.id,
+        ids::Id::from_slice(hash::keccak256(&block.bytes).as_bytes())
+    );
+
+    // a block whose value was deleted keeps its id and the id of the tx
+    db.delete(&prefix_tx_value_key(&tx_id)).await.unwrap();
+    state.inner.write().await.accepted_blocks.clear();
+    let block = state.get_block(block_id).await.unwrap();
+    assert_eq!(block.id, block_id);
+    assert_eq!(block.txs[0].id, tx_id);
+    assert_eq!(
+        block.txs[0].unsigned_transaction.get_value().await,
+        Some(tx_id.to_vec())
+    );
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use crossbeam_channel::TryRecvError;
+use tokio::sync::{broadcast, RwLock};
+
+use crate::vm;
+
+/// Maximum number of keys and grants of expired spaces removed after each
+/// accepted block.
+const SWEEP_LIMIT: usize = 128;
+
+pub struct Sweeper {
+    vm_inner: Arc<RwLock<vm::inner::Inner>>,
+}
+
+/// Removes expired spaces with their keys, values and grants as blocks are
+/// accepted. Expiry only depends on the timestamp of the accepted block, so
+/// the removal is kept out of block execution.
+impl Sweeper {
+    pub fn new(vm_inner: Arc<RwLock<vm::inner::Inner>>) -> Self {
+        Self { vm_inner }
+    }
+
+    // Helper function initialize sweeper
+    pub async fn init(&self) -> (crossbeam_channel::Receiver<()>, broadcast::Receiver<u64>) {
+        let vm = self.vm_inner.read().await;
+        (vm.stop_rx.clone(), vm.state.subscribe_accepted().await)
+    }
+
+    /// Sweeps spaces which expired before the timestamp of each accepted
+    /// block.
+    pub async fn sweep(&self) {
+        log::debug!("starting sweep loop");
+        let (stop_ch, mut accepted_ch) = self.init().await;
+
+        while stop_ch.try_recv() == Err(TryRecvError::Empty) {
+            let block_time = match accepted_ch.recv().await {
+                Ok(block_time) => block_time,
+                // only the latest timestamp matters
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+
+            let state = self.vm_inner.read().await.state.clone();
+            match state.prune_expired(block_time, SWEEP_LIMIT).await {
+                Ok(0) => {}
+                Ok(removed) => log::debug!("sweep: removed {} entries of expired spaces", removed),
+                Err(e) => log::error!("sweep: failed to remove expired spaces: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,582 @@
+use std::io::{Error, ErrorKind, Result};
+
+use avalanche_types::ids;
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::block::{state::State, Block};
+
+use super::tx::{
+    append, base, batch, cas, claim, delete, delete_space, grant, increment, lifeline, move_space,
+    multisig, revoke, set, transfer,
+    tx::{Transaction, TransactionType},
+    unsigned,
+};
+
+/// Version prefixed to every encoded block and transaction. Bump it whenever
+/// the layout below changes.
+pub const CODEC_VERSION: u16 = 0;
+
+/// Version of encodings whose transactions carry cosigners and their
+/// cosignatures after their signature. Encodings without any cosigner keep
+/// [CODEC_VERSION].
+pub const COSIGNED_CODEC_VERSION: u16 = 1;
+
+const ADDRESS_LEN: usize = 20;
+
+/// Writes values in the big endian layout of the avalanchego linear codec.
+/// Strings are prefixed with their u16 length and byte slices with their u32
+/// length. The first error is kept and returned by [Packer::take_bytes].
+#[derive(Default)]
+pub struct Packer {
+    bytes: Vec<u8>,
+    err: Option<Error>,
+}
+
+impl Packer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pack_u8(&mut self, v: u8) {
+        self.bytes.push(v);
+    }
+
+    pub fn pack_u16(&mut self, v: u16) {
+        self.bytes.extend_from_slice(&v.to_be_bytes());
+    }
+
+    pub fn pack_u32(&mut self, v: u32) {
+        self.bytes.extend_from_slice(&v.to_be_bytes());
+    }
+
+    pub fn pack_u64(&mut self, v: u64) {
+        self.bytes.extend_from_slice(&v.to_be_bytes());
+    }
+
+    pub fn pack_bytes(&mut self, v: &[u8]) {
+        if v.len() > u32::MAX as usize {
+            self.set_err(format!("bytes too long: {}", v.len()));
+            return;
+        }
+        self.pack_u32(v.len() as u32);
+        self.bytes.extend_from_slice(v);
+    }
+
+    pub fn pack_str(&mut self, v: &str) {
+        if v.len() > u16::MAX as usize {
+            self.set_err(format!("string too long: {}", v.len()));
+            return;
+        }
+        self.pack_u16(v.len() as u16);
+        self.bytes.extend_from_slice(v.as_bytes());
+    }
+
+    pub fn pack_id(&mut self, v: &ids::Id) {
+        self.bytes.extend_from_slice(&v.to_vec());
+    }
+
+    pub fn pack_address(&mut self, v: &ethereum_types::Address) {
+        self.bytes.extend_from_slice(v.as_bytes());
+    }
+
+    fn set_err(&mut self, msg: String) {
+        if self.err.is_none() {
+            self.err = Some(Error::new(ErrorKind::InvalidInput, msg));
+        }
+    }
+
+    pub fn take_bytes(self) -> Result<Vec<u8>> {
+        match self.err {
+            Some(e) => Err(e),
+            None => Ok(self.bytes),
+        }
+    }
+}
+
+/// Reads values written by [Packer]. Every read fails with InvalidData
+/// instead of reading past the end of the input.
+pub struct Unpacker<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Unpacker<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() - self.offset < len {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "unexpected end of input: need {} bytes at offset {}",
+                    len, self.offset
+                ),
+            ));
+        }
+        let b = &self.bytes[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(b)
+    }
+
+    pub fn unpack_u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn unpack_u16(&mut self) -> Result<u16> {
+        Ok(BigEndian::read_u16(self.take(2)?))
+    }
+
+    pub fn unpack_u32(&mut self) -> Result<u32> {
+        Ok(BigEndian::read_u32(self.take(4)?))
+    }
+
+    pub fn unpack_u64(&mut self) -> Result<u64> {
+        Ok(BigEndian::read_u64(self.take(8)?))
+    }
+
+    pub fn unpack_bytes(&mut self) -> Result<Vec<u8>> {
+        let len = self.unpack_u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    pub fn unpack_str(&mut self) -> Result<String> {
+        let len = self.unpack_u16()? as usize;
+        let b = self.take(len)?.to_vec();
+        String::from_utf8(b).map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))
+    }
+
+    pub fn unpack_id(&mut self) -> Result<ids::Id> {
+        Ok(ids::Id::from_slice(self.take(ids::LEN)?))
+    }
+
+    pub fn unpack_address(&mut self) -> Result<ethereum_types::Address> {
+        Ok(ethereum_types::Address::from_slice(self.take(ADDRESS_LEN)?))
+    }
+
+    /// Fails if any input is left, so every encoding has a single valid form.
+    pub fn finish(&self) -> Result<()> {
+        if self.offset != self.bytes.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "trailing bytes: {} left after offset {}",
+                    self.bytes.len() - self.offset,
+                    self.offset
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn unpack_version(&mut self) -> Result<u16> {
+        let version = self.unpack_u16()?;
+        if version != CODEC_VERSION && version != COSIGNED_CODEC_VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported codec version: {}", version),
+            ));
+        }
+        Ok(version)
+    }
+}
+
+/// Returns the type id written in front of an unsigned transaction. The ids
+/// follow the registration order of the Go SpacesVM codec, where BaseTx is 0.
+fn type_id(typ: &TransactionType) -> Result<u32> {
+    match typ {
+        TransactionType::Claim => Ok(1),
+        TransactionType::Lifeline => Ok(2),
+        TransactionType::Set => Ok(3),
+        TransactionType::Delete => Ok(4),
+        TransactionType::Move => Ok(5),
+        TransactionType::Transfer => Ok(6),
+        // not part of the Go SpacesVM
+        TransactionType::Batch => Ok(7),
+        TransactionType::Cas => Ok(8),
+        TransactionType::Grant => Ok(9),
+        TransactionType::Revoke => Ok(10),
+        TransactionType::Multisig => Ok(11),
+        TransactionType::DeleteSpace => Ok(12),
+        TransactionType::Append => Ok(14),
+        TransactionType::Increment => Ok(15),
+        TransactionType::Unknown => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "transaction type Unknown can not be encoded",
+        )),
+    }
+}
+
+/// Type id of set txs with an expiry, content type or attributes, which
+/// follow their value. Other sets keep the type id and layout of the Go
+/// SpacesVM.
+const EXTENDED_SET_TYPE_ID: u32 = 13;
+
+pub async fn pack_unsigned(
+    packer: &mut Packer,
+    utx: &Box<dyn unsigned::Transaction + Send + Sync>,
+) -> Result<()> {
+    let typ = utx.typ().await;
+    match (&typ, utx.is_extended().await) {
+        (TransactionType::Set, true) => packer.pack_u32(EXTENDED_SET_TYPE_ID),
+        _ => packer.pack_u32(type_id(&typ)?),
+    }
+    utx.get_base_tx().await.pack(packer);
+    utx.pack(packer).await;
+    Ok(())
+}
+
+/// Unpacks an unsigned transaction. The chain id is not part of the encoding
+/// so the id of the chain the bytes were received on is used.
+pub fn unpack_unsigned(
+    unpacker: &mut Unpacker,
+    chain_id: ids::Id,
+) -> Result<Box<dyn unsigned::Transaction + Send + Sync>> {
+    let id = unpacker.unpack_u32()?;
+    let base_tx = base::Tx::unpack(unpacker, chain_id)?;
+    match id {
+        1 => Ok(Box::new(claim::Tx::unpack(base_tx, unpacker)?)),
+        2 => Ok(Box::new(lifeline::Tx::unpack(base_tx, unpacker)?)),
+        3 => Ok(Box::new(set::Tx::unpack(base_tx, unpacker)?)),
+        4 => Ok(Box::new(delete::Tx::unpack(base_tx, unpacker)?)),
+        5 => Ok(Box::new(move_space::Tx::unpack(base_tx, unpacker)?)),
+        6 => Ok(Box::new(transfer::Tx::unpack(base_tx, unpacker)?)),
+        7 => Ok(Box::new(batch::Tx::unpack(base_tx, unpacker)?)),
+        8 => Ok(Box::new(cas::Tx::unpack(base_tx, unpacker)?)),
+        9 => Ok(Box::new(grant::Tx::unpack(base_tx, unpacker)?)),
+        10 => Ok(Box::new(revoke::Tx::unpack(base_tx, unpacker)?)),
+        11 => Ok(Box::new(multisig::Tx::unpack(base_tx, unpacker)?)),
+        12 => Ok(Box::new(delete_space::Tx::unpack(base_tx, unpacker)?)),
+        EXTENDED_SET_TYPE_ID => Ok(Box::new(set::Tx::unpack_extended(base_tx, unpacker)?)),
+        14 => Ok(Box::new(append::Tx::unpack(base_tx, unpacker)?)),
+        15 => Ok(Box::new(increment::Tx::unpack(base_tx, unpacker)?)),
+        _ => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("unknown transaction type id: {}", id),
+        )),
+    }
+}
+
+async fn pack_tx(
+    packer: &mut Packer,
+    tx: &Transaction,
+    version: u16,
+    with_cosignatures: bool,
+) -> Result<()> {
+    pack_unsigned(packer, &tx.unsigned_transaction).await?;
+    packer.pack_bytes(&tx.signature);
+    if version == COSIGNED_CODEC_VERSION {
+        packer.pack_u32(tx.cosigners.len() as u32);
+        for cosigner in tx.cosigners.iter() {
+            packer.pack_address(cosigner);
+        }
+        if with_cosignatures {
+            packer.pack_u32(tx.cosignatures.len() as u32);
+            for cosignature in tx.cosignatures.iter() {
+                packer.pack_bytes(cosignature);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn unpack_tx(unpacker: &mut Unpacker, chain_id: ids::Id, version: u16) -> Result<Transaction> {
+    let unsigned_transaction = unpack_unsigned(unpacker, chain_id)?;
+    let signature = unpacker.unpack_bytes()?;
+    let mut tx = Transaction::new(unsigned_transaction, signature);
+    if version == COSIGNED_CODEC_VERSION {
+        let count = unpacker.unpack_u32()?;
+        for _ in 0..count {
+            tx.cosigners.push(unpacker.unpack_address()?);
+        }
+        let count = unpacker.unpack_u32()?;
+        for _ in 0..count {
+            tx.cosignatures.push(unpacker.unpack_bytes()?);
+        }
+    }
+    Ok(tx)
+}
+
+/// Returns the version to encode [txs] with.
+fn txs_version(txs: &[Transaction]) -> u16 {
+    if txs
+        .iter()
+        .any(|tx| !tx.cosigners.is_empty() || !tx.cosignatures.is_empty())
+    {
+        COSIGNED_CODEC_VERSION
+    } else {
+        CODEC_VERSION
+    }
+}
+
+/// Fails if bytes were encoded with cosigners although none of [txs] has
+/// any, so every encoding has a single valid form.
+fn check_txs_version(txs: &[Transaction], version: u16) -> Result<()> {
+    if version != txs_version(txs) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("codec version {} used without cosigners", version),
+        ));
+    }
+    Ok(())
+}
+
+/// Encodes a signed transaction.
+pub async fn encode_tx(tx: &Transaction) -> Result<Vec<u8>> {
+    let version = txs_version(std::slice::from_ref(tx));
+    let mut packer = Packer::new();
+    packer.pack_u16(version);
+    pack_tx(&mut packer, tx, version, true).await?;
+    packer.take_bytes()
+}
+
+/// Encodes a signed transaction without its cosignatures. The Id of a
+/// transaction is the hash of these bytes, so relayers can not give a tx a
+/// new Id by re-encoding its cosignatures.
+pub async fn encode_tx_for_id(tx: &Transaction) -> Result<Vec<u8>> {
+    let version = txs_version(std::slice::from_ref(tx));
+    let mut packer = Packer::new();
+    packer.pack_u16(version);
+    pack_tx(&mut packer, tx, version, false).await?;
+    packer.take_bytes()
+}
+
+/// Decodes a signed transaction received on [chain_id]. The caller must init
+/// the transaction to recover its sender.
+pub fn decode_tx(bytes: &[u8], chain_id: ids::Id) -> Result<Transaction> {
+    let mut unpacker = Unpacker::new(bytes);
+    let version = unpacker.unpack_version()?;
+    let tx = unpack_tx(&mut unpacker, chain_id, version)?;
+    unpacker.finish()?;
+    check_txs_version(std::slice::from_ref(&tx), version)?;
+
+    Ok(tx)
+}
+
+/// Encodes a block together with its transactions. The Id of a block is the
+/// hash of these bytes.
+pub async fn encode_block(block: &Block) -> Result<Vec<u8>> {
+    let version = txs_version(&block.txs);
+    let mut packer = Packer::new();
+    packer.pack_u16(version);
+    packer.pack_id(&block.parent);
+    packer.pack_u64(block.height);
+    packer.pack_u64(block.timestamp);
+    packer.pack_bytes(&block.data);
+    packer.pack_u32(block.txs.len() as u32);
+    for tx in block.txs.iter() {
+        pack_tx(&mut packer, tx, version, true).await?;
+    }
+    packer.take_bytes()
+}
+
+/// Decodes a block and its transactions signed for [chain_id]. Ids, bytes and
+/// status are left for the caller to initialize.
+pub async fn decode_block(bytes: &[u8], chain_id: ids::Id, state: State) -> Result<Block> {
+    let mut unpacker = Unpacker::new(bytes);
+    let version = unpacker.unpack_version()?;
+    let parent = unpacker.unpack_id()?;
+    let height = unpacker.unpack_u64()?;
+    let timestamp = unpacker.unpack_u64()?;
+    let data = unpacker.unpack_bytes()?;
+
+    let mut block = Block::new(parent, height, &data, timestamp, state);
+    let count = unpacker.unpack_u32()?;
+    for _ in 0..count {
+        block.txs.push(unpack_tx(&mut unpacker, chain_id, version)?);
+    }
+    unpacker.finish()?;
+    check_txs_version(&block.txs, version)?;
+
+    Ok(block)
+}
+
+#[cfg(test)]
+fn test_tx() -> Transaction {
+    let base_tx = base::Tx {
+        block_id: ids::Id::from_slice(&[1u8; ids::LEN]),
+        price: 2,
+        magic: 3,
+        chain_id: ids::Id::from_slice(&[4u8; ids::LEN]),
+    };
+    let utx = set::Tx {
+        base_tx,
+        space: "kvs".to_string(),
+        key: "foo".to_string(),
+        value: "bar".as_bytes().to_vec(),
+        expiry: 0,
+        content_type: String::new(),
+        attributes: Default::default(),
+    };
+    Transaction::new(Box::new(utx), vec![0xaa, 0xbb])
+}
+
+/// Expected encoding of the test set tx, written by hand.
+#[cfg(test)]
+const TEST_TX_HEX: &str = concat!(
+    "0000",                                                             // codec version
+    "00000003",                                                         // set type id
+    "0101010101010101010101010101010101010101010101010101010101010101", // block_id
+    "0000000000000003",                                                 // magic
+    "0000000000000002",                                                 // price
+    "00036b7673",                                                       // space
+    "0003666f6f",                                                       // key
+    "00000003626172",                                                   // value
+    "00000002aabb",                                                     // signature
+);
+
+#[tokio::test]
+async fn test_tx_golden() {
+    let tx = test_tx();
+    let bytes = encode_tx(&tx).await.unwrap();
+    assert_eq!(hex::encode(&bytes), TEST_TX_HEX);
+
+    let chain_id = tx.unsigned_transaction.get_base_tx().await.chain_id;
+    let decoded = decode_tx(&bytes, chain_id).unwrap();
+    assert_eq!(decoded.signature, tx.signature);
+    assert_eq!(
+        decoded.unsigned_transaction.get_base_tx().await.chain_id,
+        chain_id
+    );
+    assert_eq!(encode_tx(&decoded).await.unwrap(), bytes);
+
+    // trailing bytes and unknown versions are rejected
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(decode_tx(&trailing, chain_id).is_err());
+    let mut versioned = bytes.clone();
+    versioned[1] = 2;
+    assert!(decode_tx(&versioned, chain_id).is_err());
+    assert!(decode_tx(&bytes[..bytes.len() - 1], chain_id).is_err());
+
+    // strings are limited by their u16 length prefix
+    let mut packer = Packer::new();
+    packer.pack_str(&"a".repeat(u16::MAX as usize + 1));
+    assert!(packer.take_bytes().is_err());
+}
+
+#[tokio::test]
+async fn test_claim_tx_golden() {
+    let base_tx = base::Tx {
+        block_id: ids::Id::from_slice(&[1u8; ids::LEN]),
+        price: 2,
+        magic: 3,
+        chain_id: ids::Id::from_slice(&[4u8; ids::LEN]),
+    };
+    let utx = claim::Tx {
+        base_tx,
+        space: "kvs".to_string(),
+    };
+    let tx = Transaction::new(Box::new(utx), vec![0xaa, 0xbb]);
+
+    let bytes = encode_tx(&tx).await.unwrap();
+    let expected = concat!(
+        "0000",                                                             // codec version
+        "00000001",                                                         // claim type id
+        "0101010101010101010101010101010101010101010101010101010101010101", // block_id
+        "0000000000000003",                                                 // magic
+        "0000000000000002",                                                 // price
+        "00036b7673",                                                       // space
+        "00000002aabb",                                                     // signature
+    );
+    assert_eq!(hex::encode(&bytes), expected);
+
+    let decoded = decode_tx(&bytes, base_tx.chain_id).unwrap();
+    assert_eq!(
+        decoded.unsigned_transaction.typ().await,
+        TransactionType::Claim
+    );
+    assert_eq!(encode_tx(&decoded).await.unwrap(), bytes);
+}
+
+#[tokio::test]
+async fn test_block_golden() {
+    let mut block = Block::new(
+        ids::Id::from_slice(&[5u8; ids::LEN]),
+        6,
+        &[7u8],
+        8,
+        State::default(),
+    );
+    block.txs.push(test_tx());
+
+    let bytes = encode_block(&block).await.unwrap();
+    let expected = format!(
+        "{}{}{}{}{}{}{}",
+        "0000",                                                             // codec version
+        "0505050505050505050505050505050505050505050505050505050505050505", // parent
+        "0000000000000006",                                                 // height
+        "0000000000000008",                                                 // timestamp
+        "0000000107",                                                       // data
+        "00000001",                                                         // tx count
+        &TEST_TX_HEX[4..], // txs are packed without their own version
+    );
+    assert_eq!(hex::encode(&bytes), expected);
+
+    let decoded = decode_block(&bytes, ids::Id::empty(), State::default())
+        .await
+        .unwrap();
+    assert_eq!(decoded.parent, block.parent);
+    assert_eq!(decoded.height, 6);
+    assert_eq!(decoded.timestamp, 8);
+    assert_eq!(decoded.data, vec![7u8]);
+    assert_eq!(decoded.txs.len(), 1);
+    assert_eq!(encode_block(&decoded).await.unwrap(), bytes);
+}
+
+#[tokio::test]
+async fn test_tx_cosignatures() {
+    let mut tx = test_tx();
+    tx.cosigners = vec![
+        ethereum_types::Address::from_low_u64_be(1),
+        ethereum_types::Address::from_low_u64_be(2),
+    ];
+    tx.cosignatures = vec![vec![0xcc], vec![0xdd, 0xee]];
+    let bytes = encode_tx(&tx).await.unwrap();
+    let id_hex = format!(
+        "{}{}{}{}{}",
+        "0001",            // cosigned codec version
+        &TEST_TX_HEX[4..], // the tx as without cosigners
+        "00000002",        // cosigner count
+        "0000000000000000000000000000000000000001",
+        "0000000000000000000000000000000000000002",
+    );
+    let expected = format!(
+        "{}{}{}{}",
+        id_hex,
+        "00000002", // cosignature count
+        "00000001cc",
+        "00000002ddee",
+    );
+    assert_eq!(hex::encode(&bytes), expected);
+
+    let chain_id = tx.unsigned_transaction.get_base_tx().await.chain_id;
+    let decoded = decode_tx(&bytes, chain_id).unwrap();
+    assert_eq!(decoded.cosigners, tx.cosigners);
+    assert_eq!(decoded.cosignatures, tx.cosignatures);
+    assert_eq!(encode_tx(&decoded).await.unwrap(), bytes);
+
+    // the id bytes keep the cosigners and leave out the cosignatures
+    assert_eq!(hex::encode(encode_tx_for_id(&tx).await.unwrap()), id_hex);
+
+    // the cosigned version requires cosigners
+    let mut empty = hex::decode(TEST_TX_HEX).unwrap();
+    empty[1] = 1;
+    empty.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
+    assert!(decode_tx(&empty, chain_id).is_err());
+
+    // blocks switch versions if any of their txs is cosigned
+    let mut block = Block::new(ids::Id::empty(), 1, &[], 2, State::default());
+    block.txs.push(test_tx());
+    block.txs.push(tx);
+    let bytes = encode_block(&block).await.unwrap();
+    assert_eq!(&bytes[..2], &[0, 1]);
+    let decoded = decode_block(&bytes, ids::Id::empty(), State::default())
+        .await
+        .unwrap();
+    assert!(decoded.txs[0].cosigners.is_empty());
+    assert_eq!(decoded.txs[1].cosigners.len(), 2);
+    assert_eq!(decoded.txs[1].cosignatures.len(), 2);
+    assert_eq!(encode_block(&decoded).await.unwrap(), bytes);
+}
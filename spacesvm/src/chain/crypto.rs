@@ -1,3 +1,5 @@
+use std::io;
+
 use ripemd::Ripemd160;
 use sha3::Digest;
 
@@ -10,3 +12,73 @@ pub fn compute_hash_160(buf: &[u8]) -> Vec<u8> {
     ripe.copy_from_slice(&hash);
     ripe.to_vec()
 }
+
+/// Length of a recoverable secp256k1 signature: r, s and the recovery id.
+pub const SIGNATURE_LEN: usize = 65;
+
+/// Half of the secp256k1 curve order. Signatures with a larger s are the
+/// malleable twin of a valid signature and are rejected.
+const HALF_CURVE_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// Returns the canonical form of a recoverable signature. Wallets encode
+/// the recovery id as 27 or 28 which is mapped to 0 or 1. Signatures with a
+/// high s value are rejected so every signature has a single valid encoding.
+pub fn normalize_signature(sig: &[u8]) -> io::Result<Vec<u8>> {
+    if sig.len() != SIGNATURE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid signature length: {}", sig.len()),
+        ));
+    }
+
+    let s = &sig[32..64];
+    if s > &HALF_CURVE_ORDER[..] {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "signature s value is not in the lower half of the curve order",
+        ));
+    }
+
+    let v = match sig[64] {
+        0 | 27 => 0,
+        1 | 28 => 1,
+        v => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid signature recovery id: {}", v),
+            ))
+        }
+    };
+
+    let mut normalized = sig.to_vec();
+    normalized[64] = v;
+    Ok(normalized)
+}
+
+#[test]
+fn test_normalize_signature() {
+    let mut sig = [0u8; SIGNATURE_LEN];
+    sig[31] = 1;
+    sig[63] = 1;
+
+    // wallet style recovery ids
+    sig[64] = 27;
+    assert_eq!(normalize_signature(&sig).unwrap()[64], 0);
+    sig[64] = 28;
+    assert_eq!(normalize_signature(&sig).unwrap()[64], 1);
+    sig[64] = 1;
+    assert_eq!(normalize_signature(&sig).unwrap(), sig.to_vec());
+    sig[64] = 2;
+    assert!(normalize_signature(&sig).is_err());
+    assert!(normalize_signature(&sig[..64]).is_err());
+
+    // s at half the curve order is the largest accepted value
+    sig[64] = 0;
+    sig[32..64].copy_from_slice(&HALF_CURVE_ORDER);
+    assert!(normalize_signature(&sig).is_ok());
+    sig[63] += 1;
+    assert!(normalize_signature(&sig).is_err());
+}
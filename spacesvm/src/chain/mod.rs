@@ -1,6 +1,8 @@
+pub mod codec;
 pub mod crypto;
 pub mod storage;
 pub mod tx;
+pub mod validation;
 pub mod vm;
 
 #[cfg(test)]
@@ -1,4 +1,13 @@
+//! Keys of a space are stored as value metas under [KEY_PREFIX]. A value meta
+//! references its value by the id of the tx, or batch op, which wrote it and
+//! the value itself is stored under that id with [TX_VALUE_PREFIX]. Values are
+//! deleted together with their key, when it is overwritten, deleted or expires
+//! and when its space is removed. Accepted blocks store a set tx with its value
+//! replaced by the tx id, so a block whose values were deleted is no longer
+//! restored with the bytes it was accepted with (see [state::State::get_block]).
+
 use std::{
+    collections::BTreeMap,
     io::{Error, ErrorKind, Result},
     str,
 };
@@ -15,6 +24,7 @@ use crate::{
         Block,
     },
     chain::crypto,
+    genesis::Genesis,
 };
 
 use super::tx::{self, claim, Transaction};
@@ -25,6 +35,10 @@ const TX_PREFIX: u8 = 0x1;
 const TX_VALUE_PREFIX: u8 = 0x2;
 const INFO_PREFIX: u8 = 0x3;
 const KEY_PREFIX: u8 = 0x4;
+const BALANCE_PREFIX: u8 = 0x5;
+const EXPIRY_PREFIX: u8 = 0x6;
+const ACL_PREFIX: u8 = 0x7;
+const TTL_PREFIX: u8 = 0x8;
 
 pub const BYTE_DELIMITER: u8 = b'/';
 
@@ -36,6 +50,8 @@ pub async fn set_transaction(
     return db.put(&k, &vec![]).await;
 }
 
+/// Deletes a key and releases the storage it used from the usage of the
+/// space.
 pub async fn delete_space_key(
     db: &mut Box<dyn avalanche_types::subnet::rpc::database::Database + Send + Sync>,
     space: &[u8],
@@ -46,8 +62,14 @@ pub async fn delete_space_key(
             ErrorKind::InvalidData,
             format!("space not found"),
         )),
-        Some(info) => {
-            db.delete(&space_value_key(info.raw_space, key))
+        Some(mut info) => {
+            let k = space_value_key(info.raw_space, key);
+            if let Some(prev) = get_raw_value_meta(db, &k).await? {
+                info.usage = release_usage(info.usage, key, &prev);
+                put_space_info(db, space, info, 0).await?;
+                delete_value(db, &prev).await?;
+            }
+            db.delete(&k)
                 .await
                 .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
             Ok(())
@@ -55,6 +77,43 @@ pub async fn delete_space_key(
     }
 }
 
+/// Returns the usage of a space once the value of [key] is removed.
+fn release_usage(usage: claim::Usage, key: &[u8], vmeta: &ValueMeta) -> claim::Usage {
+    claim::Usage {
+        keys: usage.keys.saturating_sub(1),
+        bytes: usage.bytes.saturating_sub(vmeta.usage_bytes(key)),
+        stored: usage.stored.saturating_sub(vmeta.size),
+    }
+}
+
+/// Deletes the value [vmeta] references.
+async fn delete_value(
+    db: &mut Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    vmeta: &ValueMeta,
+) -> Result<()> {
+    db.delete(&prefix_tx_value_key(&vmeta.tx_id)).await
+}
+
+/// Reads the value meta stored under the full space value key [k].
+async fn get_raw_value_meta(
+    db: &Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    k: &[u8],
+) -> Result<Option<ValueMeta>> {
+    match db.get(k).await {
+        Err(e) => {
+            if is_not_found(&e) {
+                return Ok(None);
+            }
+            Err(e)
+        }
+        Ok(value) => {
+            let vmeta: ValueMeta = serde_json::from_slice(&value)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+            Ok(Some(vmeta))
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Default, Debug)]
 pub struct ValueMeta {
     pub size: u64,
@@ -63,11 +122,52 @@ pub struct ValueMeta {
 
     pub created: u64,
     pub updated: u64,
+
+    /// Unix timestamp after which the key is treated as missing. Zero if the
+    /// key does not expire.
+    #[serde(default)]
+    pub expiry: u64,
+
+    /// MIME type of the value such as "application/json". Empty if unknown.
+    #[serde(default)]
+    pub content_type: String,
+
+    /// User defined name and value pairs describing the value.
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl ValueMeta {
+    /// Returns true if the key expired before the given time.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expiry != 0 && self.expiry < now
+    }
+
+    /// Returns the bytes [key] uses with this value, counting the key, the
+    /// value and its metadata.
+    pub fn usage_bytes(&self, key: &[u8]) -> u64 {
+        let meta = self.content_type.len()
+            + self
+                .attributes
+                .iter()
+                .map(|(name, value)| name.len() + value.len())
+                .sum::<usize>();
+        (key.len() as u64)
+            .saturating_add(self.size)
+            .saturating_add(meta as u64)
+    }
 }
 
 pub async fn submit(state: &state::State, txs: &mut Vec<tx::tx::Transaction>) -> Result<()> {
     let now = Utc::now().timestamp() as u64;
-    let db = &state.get_db().await;
+
+    // txs are checked against the last accepted state in a throwaway overlay
+    // so that validation never changes the database
+    let last_accepted = state.get_last_accepted().await?;
+    let db: Box<dyn subnet::rpc::database::Database + Send + Sync> =
+        Box::new(state.new_overlay(last_accepted).await);
+    let window = state.get_genesis().await.lookback_window;
+    let mut context = state.recent_context(last_accepted, window).await?;
 
     for tx in txs.iter_mut() {
         tx.init()
@@ -78,18 +178,20 @@ pub async fn submit(state: &state::State, txs: &mut Vec<tx::tx::Transaction>) ->
         }
         let dummy_block = Block::new_dummy(now, tx.to_owned(), state.clone());
 
-        tx.execute(&db, &dummy_block)
-            .await
-            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+        tx.execute(&db, &dummy_block, &context).await?;
+        context.recent_tx_ids.insert(tx.id);
     }
 
     Ok(())
 }
 
+/// Returns the value of [key], or None if the space does not exist or the key
+/// expired before [now].
 pub async fn get_value(
     db: &Box<dyn avalanche_types::subnet::rpc::database::Database + Send + Sync>,
     space: &[u8],
     key: &[u8],
+    now: u64,
 ) -> Result<Option<Vec<u8>>> {
     let info: Option<tx::claim::Info> = match get_space_info(db, space).await {
         Ok(info) => info,
@@ -104,13 +206,15 @@ pub async fn get_value(
         return Ok(None);
     }
 
-    let value = db
-        .get(&space_value_key(info.unwrap().raw_space, key))
-        .await
-        .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
-
-    let vmeta: ValueMeta = serde_json::from_slice(&value)
-        .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+    // keys which were never set are not found rather than an error
+    let vmeta = match get_raw_value_meta(db, &space_value_key(info.unwrap().raw_space, key)).await?
+    {
+        Some(vmeta) => vmeta,
+        None => return Ok(None),
+    };
+    if vmeta.is_expired(now) {
+        return Ok(None);
+    }
 
     let tx_id = vmeta.tx_id;
 
@@ -126,35 +230,33 @@ pub async fn get_value(
     Ok(Some(value))
 }
 
+/// Returns the value meta of [key], or None if the space does not exist or
+/// the key expired before [now].
 pub async fn get_value_meta(
     db: &Box<dyn avalanche_types::subnet::rpc::database::Database + Send + Sync>,
     space: &[u8],
     key: &[u8],
+    now: u64,
 ) -> Result<Option<ValueMeta>> {
     match get_space_info(&db, space).await? {
         None => Ok(None),
-        Some(info) => match db.get(&space_value_key(info.raw_space, key)).await {
-            Err(e) => {
-                if is_not_found(&e) {
-                    return Ok(None);
-                }
-                Err(e)
-            }
-            Ok(value) => {
-                let vmeta: ValueMeta = serde_json::from_slice(&value)
-                    .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
-                Ok(Some(vmeta))
-            }
-        },
+        Some(info) => Ok(
+            get_raw_value_meta(db, &space_value_key(info.raw_space, key))
+                .await?
+                .filter(|vmeta| !vmeta.is_expired(now)),
+        ),
     }
 }
 
-// Attempts to write the value
+// Attempts to write the value, which the caller stores under the tx id of
+// [vmeta]. The usage of the space is updated and must stay within the quota
+// of the genesis.
 pub async fn put_space_key(
     db: &mut Box<dyn subnet::rpc::database::Database + Send + Sync>,
     space: &[u8],
     key: &[u8],
     vmeta: ValueMeta,
+    genesis: &Genesis,
 ) -> Result<()> {
     let resp = get_space_info(db, space)
         .await
@@ -162,8 +264,59 @@ pub async fn put_space_key(
     if resp.is_none() {
         return Err(Error::new(ErrorKind::NotFound, format!("space not found")));
     }
+    let mut info = resp.unwrap();
+
+    let k = space_value_key(info.raw_space, key);
+    let mut usage = info.usage;
+    let prev = get_raw_value_meta(db, &k).await?;
+    if let Some(prev) = &prev {
+        usage = release_usage(usage, key, prev);
+    }
+    usage.keys = usage.keys.saturating_add(1);
+    usage.bytes = usage.bytes.saturating_add(vmeta.usage_bytes(key));
+    usage.stored = usage.stored.saturating_add(vmeta.size);
+    if usage.keys > genesis.max_space_keys {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "space quota exceeded: {} keys (max: {})",
+                usage.keys, genesis.max_space_keys
+            ),
+        ));
+    }
+    if usage.bytes > genesis.max_space_bytes {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "space quota exceeded: {} bytes (max: {})",
+                usage.bytes, genesis.max_space_bytes
+            ),
+        ));
+    }
+    if usage.stored > genesis.max_space_stored_bytes {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "space quota exceeded: {} stored bytes (max: {})",
+                usage.stored, genesis.max_space_stored_bytes
+            ),
+        ));
+    }
+    info.usage = usage;
+    let r_space = info.raw_space;
+    put_space_info(db, space, info, 0).await?;
+
+    // expiring keys are indexed so they can be removed once they expire
+    if vmeta.expiry != 0 {
+        db.put(&prefix_ttl_key(vmeta.expiry, r_space, key), space)
+            .await?;
+    }
+
+    // the value of an overwritten key is replaced by the one of [vmeta]
+    if let Some(prev) = prev.filter(|prev| prev.tx_id != vmeta.tx_id) {
+        delete_value(db, &prev).await?;
+    }
 
-    let k = space_value_key(resp.unwrap().raw_space, key);
     log::info!("put_value key: {:?}", k);
     let rv_meta = serde_json::to_vec(&vmeta)
         .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
@@ -172,12 +325,13 @@ pub async fn put_space_key(
 }
 
 /// Attempts to store the space info by using a key 'space_info_key' with the value
-/// being serialized space info.
+/// being serialized space info. The expiry index entry written for [last_expiry]
+/// is replaced by one for the current expiry of the space.
 pub async fn put_space_info(
     db: &mut Box<dyn subnet::rpc::database::Database + Send + Sync>,
     space: &[u8],
     mut info: claim::Info,
-    _last_expiry: u64,
+    last_expiry: u64,
 ) -> Result<()> {
     // If [raw_space] is empty, this is a new space.
     if info.raw_space.is_empty() {
@@ -195,7 +349,15 @@ pub async fn put_space_info(
     log::info!("put_space_info key: {:?}", key);
     log::info!("put_space_info value: {:?}", value);
 
-    db.put(key, &value).await
+    db.put(key, &value).await?;
+
+    // Keep the expiry index pointing at the current expiry of the space.
+    if last_expiry != 0 && last_expiry != info.expiry {
+        db.delete(&prefix_expiry_key(last_expiry, info.raw_space))
+            .await?;
+    }
+    db.put(&prefix_expiry_key(info.expiry, info.raw_space), space)
+        .await
 }
 
 // Attempts to get info from a space.
@@ -224,13 +386,257 @@ pub async fn raw_space(space: &[u8], block_time: u64) -> Result<ids::short::Id>
     let mut r: Vec<u8> = Vec::new();
     r.extend_from_slice(space);
     r.push(BYTE_DELIMITER);
-    r.resize(space.len() + 1 + 8, 20);
-    BigEndian::write_u64(&mut r[space.len() + 1..].to_vec(), block_time);
+    r.resize(space.len() + 1 + 8, 0);
+    BigEndian::write_u64(&mut r[space.len() + 1..], block_time);
     let hash = crypto::compute_hash_160(&r);
 
     Ok(ids::short::Id::from_slice(&hash))
 }
 
+/// Removes spaces which expired before [now] together with their keys,
+/// values and grants, deleting at most [limit] keys and grants. A space with
+/// more left is finished by later calls. Returns the number of keys and
+/// grants removed.
+pub async fn prune_expired(
+    db: &mut Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    now: u64,
+    limit: usize,
+) -> Result<usize> {
+    // expiry index keys are ordered by their big endian expiry
+    let entries = scan_prefix(db, &[EXPIRY_PREFIX, BYTE_DELIMITER], limit).await?;
+
+    let mut removed = 0;
+    for (index_key, space) in entries.iter() {
+        if index_key.len() != 2 + 8 + 1 + SHORT_ID_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid expiry key length: {}", index_key.len()),
+            ));
+        }
+        let expiry = BigEndian::read_u64(&index_key[2..10]);
+        if expiry >= now || removed >= limit {
+            break;
+        }
+        let r_space = ids::short::Id::from_slice(&index_key[11..]);
+
+        // the space may have been claimed again under a new raw space
+        if let Some(info) = get_space_info(db, space).await? {
+            if info.raw_space == r_space && info.is_expired(now) {
+                db.delete(&space_info_key(space)).await?;
+            }
+        }
+
+        let budget = limit - removed;
+        let deleted = delete_raw_space_entries(db, r_space, budget).await?;
+        removed += deleted;
+        log::debug!(
+            "prune_expired: space: {:?} expiry: {} removed: {}",
+            str::from_utf8(space),
+            expiry,
+            deleted
+        );
+        if deleted == budget {
+            // entries may be left, the index is kept until they are removed
+            break;
+        }
+        db.delete(index_key).await?;
+    }
+
+    Ok(removed)
+}
+
+/// Removes up to [limit] keys which expired before [now] together with their
+/// values, releasing the storage they used. Index entries of keys which were deleted or overwritten since are dropped.
+/// Returns the number of keys removed.
+pub async fn prune_expired_keys(
+    db: &mut Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    now: u64,
+    limit: usize,
+) -> Result<usize> {
+    // index keys are ordered by their big endian expiry
+    let entries = scan_prefix(db, &[TTL_PREFIX, BYTE_DELIMITER], limit).await?;
+
+    let mut pruned = 0;
+    for (index_key, space) in entries.iter() {
+        if index_key.len() <= 2 + 8 + 1 + SHORT_ID_LEN + 1 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid ttl key length: {}", index_key.len()),
+            ));
+        }
+        let expiry = BigEndian::read_u64(&index_key[2..10]);
+        if expiry >= now {
+            break;
+        }
+        let r_space = ids::short::Id::from_slice(&index_key[11..11 + SHORT_ID_LEN]);
+        let key = &index_key[11 + SHORT_ID_LEN + 1..];
+
+        // the space may have been removed or claimed again under a new raw
+        // space, and the key written again since. Keys of expired spaces are
+        // left to the sweeper, which may have removed them already.
+        let info = get_space_info(db, space).await?;
+        if info.map_or(false, |info| {
+            info.raw_space == r_space && !info.is_expired(now)
+        }) {
+            if let Some(vmeta) = get_raw_value_meta(db, &space_value_key(r_space, key)).await? {
+                if vmeta.expiry == expiry {
+                    delete_space_key(db, space, key).await?;
+                    pruned += 1;
+                }
+            }
+        }
+        db.delete(index_key).await?;
+    }
+
+    Ok(pruned)
+}
+
+/// Removes a live space described by [info] together with its keys, values
+/// and grants, so its name can be claimed again. Returns the number of keys
+/// and grants removed.
+pub async fn delete_space(
+    db: &mut Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    space: &[u8],
+    info: &claim::Info,
+) -> Result<usize> {
+    let removed = delete_raw_space_entries(db, info.raw_space, usize::MAX).await?;
+    db.delete(&prefix_expiry_key(info.expiry, info.raw_space))
+        .await?;
+    db.delete(&space_info_key(space)).await?;
+
+    Ok(removed)
+}
+
+/// Write permission the owner of a space granted to another address.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Grant {
+    /// Only keys starting with the prefix may be written. Empty for all keys.
+    pub prefix: String,
+
+    /// Unix timestamp after which the grant no longer applies. Zero if the
+    /// grant does not expire.
+    pub expiry: u64,
+
+    /// Owner epoch of the space when the grant was given. Moving the space
+    /// or changing its multisig revokes the grant.
+    #[serde(default)]
+    pub owner_epoch: u64,
+}
+
+impl Grant {
+    /// Returns true if the grant allows writing [key] at [block_time].
+    pub fn allows(&self, key: &[u8], block_time: u64) -> bool {
+        (self.expiry == 0 || block_time <= self.expiry) && key.starts_with(self.prefix.as_bytes())
+    }
+}
+
+pub async fn put_grant(
+    db: &mut Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    r_space: ids::short::Id,
+    address: &ethereum_types::Address,
+    grant: &Grant,
+) -> Result<()> {
+    let value =
+        serde_json::to_vec(grant).map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+    db.put(&prefix_acl_key(r_space, address), &value).await
+}
+
+pub async fn get_grant(
+    db: &Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    r_space: ids::short::Id,
+    address: &ethereum_types::Address,
+) -> Result<Option<Grant>> {
+    match db.get(&prefix_acl_key(r_space, address)).await {
+        Err(e) => {
+            if is_not_found(&e) {
+                return Ok(None);
+            }
+            Err(e)
+        }
+        Ok(value) => {
+            let grant: Grant = serde_json::from_slice(&value)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+            Ok(Some(grant))
+        }
+    }
+}
+
+pub async fn delete_grant(
+    db: &mut Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    r_space: ids::short::Id,
+    address: &ethereum_types::Address,
+) -> Result<()> {
+    db.delete(&prefix_acl_key(r_space, address)).await
+}
+
+/// Returns true if a tx of [sender] and [cosigners] may write [key] in the
+/// space described by [info], either as its owner or through a grant of the
+/// sender given by the current owner.
+pub async fn can_write(
+    db: &Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    info: &claim::Info,
+    sender: &ethereum_types::Address,
+    cosigners: &[ethereum_types::Address],
+    key: &[u8],
+    block_time: u64,
+) -> Result<bool> {
+    if info.is_owned_by(sender, cosigners) {
+        return Ok(true);
+    }
+    Ok(get_grant(db, info.raw_space, sender)
+        .await?
+        .map_or(false, |grant| {
+            grant.owner_epoch == info.owner_epoch && grant.allows(key, block_time)
+        }))
+}
+
+/// Deletes up to [limit] keys and grants stored under [r_space] together
+/// with the values of the keys. Returns the number of entries removed, less
+/// than [limit] once none are left.
+async fn delete_raw_space_entries(
+    db: &mut Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    r_space: ids::short::Id,
+    limit: usize,
+) -> Result<usize> {
+    let mut entries = scan_prefix(db, &space_value_key(r_space, &[]), limit).await?;
+    for (_, value) in entries.iter() {
+        let vmeta: ValueMeta = serde_json::from_slice(value)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        delete_value(db, &vmeta).await?;
+    }
+    if entries.len() < limit {
+        let mut prefix = vec![ACL_PREFIX, BYTE_DELIMITER];
+        prefix.extend_from_slice(r_space.as_ref());
+        prefix.push(BYTE_DELIMITER);
+        entries.extend(scan_prefix(db, &prefix, limit - entries.len()).await?);
+    }
+    for (key, _) in entries.iter() {
+        db.delete(key).await?;
+    }
+    Ok(entries.len())
+}
+
+/// Returns up to [limit] key/value pairs stored under [prefix] in key order.
+async fn scan_prefix(
+    db: &Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    prefix: &[u8],
+    limit: usize,
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let mut iter = db.new_iterator_with_prefix(prefix).await?;
+
+    let mut entries: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+    while entries.len() < limit && iter.next().await? {
+        let key = iter.key().await?.to_vec();
+        let value = iter.value().await?.to_vec();
+        entries.push((key, value));
+    }
+    let result = iter.error().await;
+    iter.release().await;
+    result?;
+
+    Ok(entries)
+}
+
 /// Returns true if a space with the same name already exists.
 pub async fn has_space(
     db: &Box<dyn subnet::rpc::database::Database + Send + Sync>,
@@ -239,6 +645,79 @@ pub async fn has_space(
     db.has(&space_info_key(space)).await
 }
 
+/// Returns the balance of an address. Addresses which were never funded
+/// have a balance of zero.
+pub async fn get_balance(
+    db: &Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    address: &ethereum_types::Address,
+) -> Result<u64> {
+    match db.get(&prefix_balance_key(address)).await {
+        Err(e) => {
+            if is_not_found(&e) {
+                return Ok(0);
+            }
+            Err(e)
+        }
+        Ok(value) => {
+            if value.len() != 8 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid balance length: {}", value.len()),
+                ));
+            }
+            Ok(BigEndian::read_u64(&value))
+        }
+    }
+}
+
+/// Stores the balance of an address as a big endian u64.
+pub async fn set_balance(
+    db: &mut Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    address: &ethereum_types::Address,
+    balance: u64,
+) -> Result<()> {
+    let mut value = [0u8; 8];
+    BigEndian::write_u64(&mut value, balance);
+    db.put(&prefix_balance_key(address), &value).await
+}
+
+/// Credits units to the balance of an address and returns the new balance.
+pub async fn add_balance(
+    db: &mut Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    address: &ethereum_types::Address,
+    units: u64,
+) -> Result<u64> {
+    let balance = get_balance(db, address).await?;
+    let balance = balance.checked_add(units).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("balance overflow: {:?}", address),
+        )
+    })?;
+    set_balance(db, address, balance).await?;
+    Ok(balance)
+}
+
+/// Debits units from the balance of an address and returns the new balance.
+pub async fn sub_balance(
+    db: &mut Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    address: &ethereum_types::Address,
+    units: u64,
+) -> Result<u64> {
+    let balance = get_balance(db, address).await?;
+    let balance = balance.checked_sub(units).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "insufficient balance: {:?} has {} needs {}",
+                address, balance, units
+            ),
+        )
+    })?;
+    set_balance(db, address, balance).await?;
+    Ok(balance)
+}
+
 /// 'KEY_PREFIX' + 'BYTE_DELIMITER' + [r_space] + 'BYTE_DELIMITER' + [key]
 pub fn space_value_key(r_space: ids::short::Id, key: &[u8]) -> Vec<u8> {
     let mut k: Vec<u8> = Vec::with_capacity(2 + SHORT_ID_LEN + 1 + key.len());
@@ -286,6 +765,50 @@ pub fn prefix_tx_value_key(tx_id: &ids::Id) -> Vec<u8> {
     k
 }
 
+/// 'EXPIRY_PREFIX' + 'BYTE_DELIMITER' + [expiry] + 'BYTE_DELIMITER' + [r_space]
+pub fn prefix_expiry_key(expiry: u64, r_space: ids::short::Id) -> Vec<u8> {
+    let mut k: Vec<u8> = Vec::with_capacity(2 + 8 + 1 + SHORT_ID_LEN);
+    k.push(EXPIRY_PREFIX);
+    k.push(BYTE_DELIMITER);
+    k.extend_from_slice(&expiry.to_be_bytes());
+    k.push(BYTE_DELIMITER);
+    k.extend_from_slice(r_space.as_ref());
+    k
+}
+
+/// 'TTL_PREFIX' + 'BYTE_DELIMITER' + [expiry] + 'BYTE_DELIMITER' + [r_space] + 'BYTE_DELIMITER' + [key]
+pub fn prefix_ttl_key(expiry: u64, r_space: ids::short::Id, key: &[u8]) -> Vec<u8> {
+    let mut k: Vec<u8> = Vec::with_capacity(2 + 8 + 1 + SHORT_ID_LEN + 1 + key.len());
+    k.push(TTL_PREFIX);
+    k.push(BYTE_DELIMITER);
+    k.extend_from_slice(&expiry.to_be_bytes());
+    k.push(BYTE_DELIMITER);
+    k.extend_from_slice(r_space.as_ref());
+    k.push(BYTE_DELIMITER);
+    k.extend_from_slice(key);
+    k
+}
+
+/// 'ACL_PREFIX' + 'BYTE_DELIMITER' + [r_space] + 'BYTE_DELIMITER' + 'address'
+pub fn prefix_acl_key(r_space: ids::short::Id, address: &ethereum_types::Address) -> Vec<u8> {
+    let mut k: Vec<u8> = Vec::with_capacity(2 + SHORT_ID_LEN + 1 + address.as_bytes().len());
+    k.push(ACL_PREFIX);
+    k.push(BYTE_DELIMITER);
+    k.extend_from_slice(r_space.as_ref());
+    k.push(BYTE_DELIMITER);
+    k.extend_from_slice(address.as_bytes());
+    k
+}
+
+/// 'BALANCE_PREFIX' + 'BYTE_DELIMITER' + 'address'
+pub fn prefix_balance_key(address: &ethereum_types::Address) -> Vec<u8> {
+    let mut k: Vec<u8> = Vec::with_capacity(2 + address.as_bytes().len());
+    k.push(BALANCE_PREFIX);
+    k.push(BYTE_DELIMITER);
+    k.extend_from_slice(address.as_bytes());
+    k
+}
+
 /// Returns false if the io::Error is ErrorKind::NotFound and contains a string "not found".
 pub fn is_not_found(error: &Error) -> bool {
     if error.kind() == ErrorKind::NotFound && error.to_string().contains("not found") {
@@ -329,6 +852,19 @@ fn test_prefix() {
             2, 47, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             0, 0, 0, 0, 0
         ]
+    );
+    // 'BALANCE_PREFIX' [5] + 'BYTE_DELIMITER' [47] + 'address' 0 x 20
+    assert_eq!(
+        prefix_balance_key(&ethereum_types::Address::zero()),
+        [5, 47, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+    );
+    // 'EXPIRY_PREFIX' [6] + 'BYTE_DELIMITER' [47] + 'expiry' [0, 0, 0, 0, 0, 0, 1, 0] + 'BYTE_DELIMITER' [47] + [raw_space] 0 x 20
+    assert_eq!(
+        prefix_expiry_key(256, ids::short::Id::empty()),
+        [
+            6, 47, 0, 0, 0, 0, 0, 0, 1, 0, 47, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0
+        ]
     )
 }
 
@@ -339,10 +875,14 @@ async fn test_raw_space() {
     assert_eq!(
         resp.unwrap(),
         ids::short::Id::from_slice(&[
-            230, 185, 125, 2, 27, 125, 127, 228, 212, 79, 188, 214, 107, 248, 146, 237, 254, 112,
-            153, 17
+            28, 196, 105, 174, 208, 254, 253, 229, 213, 10, 32, 26, 54, 105, 74, 64, 119, 12, 91,
+            61
         ])
-    )
+    );
+
+    // the block time is part of the raw space so a reclaimed space starts empty
+    let resp = raw_space("kvs".as_bytes(), 1).await;
+    assert_ne!(resp.unwrap(), raw_space("kvs".as_bytes(), 0).await.unwrap());
 }
 
 #[tokio::test]
@@ -358,8 +898,12 @@ async fn test_space_info_rt() {
     let new_info = Info {
         created: 0,
         updated: 1,
+        expiry: 10,
         owner: H160::default(),
         raw_space: ids::short::Id::empty(),
+        usage: Default::default(),
+        multisig: None,
+        owner_epoch: 0,
     };
     let mut db = subnet::rpc::database::memdb::Database::new();
     // put
@@ -374,9 +918,323 @@ async fn test_space_info_rt() {
     assert_eq!(
         info.raw_space,
         ids::short::Id::from_slice(&[
-            230, 185, 125, 2, 27, 125, 127, 228, 212, 79, 188, 214, 107, 248, 146, 237, 254, 112,
-            153, 17
+            28, 196, 105, 174, 208, 254, 253, 229, 213, 10, 32, 26, 54, 105, 74, 64, 119, 12, 91,
+            61
         ])
     );
     assert_eq!(info.updated, 1);
+
+    // expiry index follows the space
+    let key = prefix_expiry_key(10, info.raw_space);
+    assert_eq!(db.get(&key).await.unwrap(), space.to_vec());
+}
+
+#[tokio::test]
+async fn test_prune_expired() {
+    use super::tx::claim::Info;
+    use ethereum_types::H160;
+
+    let mut db = subnet::rpc::database::memdb::Database::new();
+    for (space, expiry, id) in [("old", 10, 1u8), ("new", 100, 2u8)] {
+        let info = Info {
+            created: 0,
+            updated: 0,
+            expiry,
+            owner: H160::default(),
+            raw_space: ids::short::Id::empty(),
+            usage: Default::default(),
+            multisig: None,
+            owner_epoch: 0,
+        };
+        put_space_info(&mut db, space.as_bytes(), info, 0)
+            .await
+            .unwrap();
+
+        let tx_id = ids::Id::from_slice(&[id; ids::LEN]);
+        let vmeta = ValueMeta {
+            size: 3,
+            tx_id,
+            created: 0,
+            updated: 0,
+            expiry: 0,
+            ..Default::default()
+        };
+        put_space_key(
+            &mut db,
+            space.as_bytes(),
+            "foo".as_bytes(),
+            vmeta,
+            &Genesis::default(),
+        )
+        .await
+        .unwrap();
+        db.put(&prefix_tx_value_key(&tx_id), "bar".as_bytes())
+            .await
+            .unwrap();
+    }
+    let old_info = get_space_info(&db, "old".as_bytes())
+        .await
+        .unwrap()
+        .unwrap();
+
+    put_space_key(
+        &mut db,
+        "old".as_bytes(),
+        "bar".as_bytes(),
+        ValueMeta::default(),
+        &Genesis::default(),
+    )
+    .await
+    .unwrap();
+
+    // nothing expired yet
+    assert_eq!(prune_expired(&mut db, 10, 16).await.unwrap(), 0);
+
+    // only the first space expired, its keys are removed over several calls
+    assert_eq!(prune_expired(&mut db, 11, 1).await.unwrap(), 1);
+    assert!(get_space_info(&db, "old".as_bytes())
+        .await
+        .unwrap()
+        .is_none());
+    assert!(db
+        .has(&prefix_expiry_key(10, old_info.raw_space))
+        .await
+        .unwrap());
+    assert_eq!(prune_expired(&mut db, 11, 16).await.unwrap(), 1);
+    assert!(!db
+        .has(&space_value_key(old_info.raw_space, "foo".as_bytes()))
+        .await
+        .unwrap());
+    assert!(!db
+        .has(&prefix_expiry_key(10, old_info.raw_space))
+        .await
+        .unwrap());
+    assert_eq!(prune_expired(&mut db, 11, 16).await.unwrap(), 0);
+
+    // values are removed with their keys
+    assert!(!db
+        .has(&prefix_tx_value_key(&ids::Id::from_slice(&[1u8; ids::LEN])))
+        .await
+        .unwrap());
+    assert_eq!(
+        get_value(&db, "new".as_bytes(), "foo".as_bytes(), 0)
+            .await
+            .unwrap(),
+        Some("bar".as_bytes().to_vec())
+    );
+}
+
+#[tokio::test]
+async fn test_space_usage() {
+    use super::tx::claim::{Info, Usage};
+    use ethereum_types::H160;
+
+    let mut db = subnet::rpc::database::memdb::Database::new();
+    let space = "kvs".as_bytes();
+    let info = Info {
+        created: 0,
+        updated: 0,
+        expiry: 10,
+        owner: H160::default(),
+        raw_space: ids::short::Id::empty(),
+        usage: Default::default(),
+        multisig: None,
+        owner_epoch: 0,
+    };
+    put_space_info(&mut db, space, info, 0).await.unwrap();
+
+    let genesis = Genesis {
+        max_space_keys: 2,
+        max_space_bytes: 16,
+        max_space_stored_bytes: 15,
+        ..Default::default()
+    };
+    let vmeta = |size: u64| ValueMeta {
+        size,
+        tx_id: ids::Id::empty(),
+        created: 0,
+        updated: 0,
+        expiry: 0,
+        ..Default::default()
+    };
+
+    // keys count their own length and the size of their value
+    put_space_key(&mut db, space, "foo".as_bytes(), vmeta(3), &genesis)
+        .await
+        .unwrap();
+    assert_eq!(
+        get_space_info(&db, space).await.unwrap().unwrap().usage,
+        Usage {
+            keys: 1,
+            bytes: 6,
+            stored: 3
+        }
+    );
+
+    // overwriting a key replaces its usage
+    put_space_key(&mut db, space, "foo".as_bytes(), vmeta(5), &genesis)
+        .await
+        .unwrap();
+    assert_eq!(
+        get_space_info(&db, space).await.unwrap().unwrap().usage,
+        Usage {
+            keys: 1,
+            bytes: 8,
+            stored: 5
+        }
+    );
+
+    // the quota covers both keys and bytes
+    let err = put_space_key(&mut db, space, "bar".as_bytes(), vmeta(6), &genesis)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("quota exceeded"));
+    put_space_key(&mut db, space, "bar".as_bytes(), vmeta(5), &genesis)
+        .await
+        .unwrap();
+    assert_eq!(
+        get_space_info(&db, space).await.unwrap().unwrap().usage,
+        Usage {
+            keys: 2,
+            bytes: 16,
+            stored: 10
+        }
+    );
+    let err = put_space_key(&mut db, space, "baz".as_bytes(), vmeta(0), &genesis)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("quota exceeded"));
+
+    // deleting a key releases its usage
+    delete_space_key(&mut db, space, "foo".as_bytes())
+        .await
+        .unwrap();
+    assert_eq!(
+        get_space_info(&db, space).await.unwrap().unwrap().usage,
+        Usage {
+            keys: 1,
+            bytes: 8,
+            stored: 5
+        }
+    );
+
+    // the released bytes can be written again
+    put_space_key(&mut db, space, "foo".as_bytes(), vmeta(5), &genesis)
+        .await
+        .unwrap();
+    assert_eq!(
+        get_space_info(&db, space).await.unwrap().unwrap().usage,
+        Usage {
+            keys: 2,
+            bytes: 16,
+            stored: 10
+        }
+    );
+
+    // values count against the stored bytes on their own
+    let genesis = Genesis {
+        max_space_stored_bytes: 9,
+        ..genesis
+    };
+    let err = put_space_key(&mut db, space, "foo".as_bytes(), vmeta(5), &genesis)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("stored bytes"));
+}
+
+#[tokio::test]
+async fn test_prune_expired_keys() {
+    use super::tx::claim::{Info, Usage};
+    use ethereum_types::H160;
+
+    let mut db = subnet::rpc::database::memdb::Database::new();
+    let space = "kvs".as_bytes();
+    let info = Info {
+        created: 0,
+        updated: 0,
+        expiry: 100,
+        owner: H160::default(),
+        raw_space: ids::short::Id::empty(),
+        usage: Default::default(),
+        multisig: None,
+        owner_epoch: 0,
+    };
+    put_space_info(&mut db, space, info, 0).await.unwrap();
+
+    for (key, expiry, id) in [("foo", 10, 1u8), ("bar", 20, 2u8), ("baz", 0, 3u8)] {
+        let tx_id = ids::Id::from_slice(&[id; ids::LEN]);
+        let vmeta = ValueMeta {
+            size: 3,
+            tx_id,
+            created: 0,
+            updated: 0,
+            expiry,
+            ..Default::default()
+        };
+        put_space_key(&mut db, space, key.as_bytes(), vmeta, &Genesis::default())
+            .await
+            .unwrap();
+        db.put(&prefix_tx_value_key(&tx_id), "val".as_bytes())
+            .await
+            .unwrap();
+    }
+
+    // expired keys are treated as missing before they are removed
+    assert!(get_value(&db, space, "foo".as_bytes(), 11)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(get_value_meta(&db, space, "foo".as_bytes(), 11)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(get_value(&db, space, "foo".as_bytes(), 10)
+        .await
+        .unwrap()
+        .is_some());
+
+    // overwritten keys keep their new expiry
+    let vmeta = ValueMeta {
+        size: 3,
+        tx_id: ids::Id::from_slice(&[2u8; ids::LEN]),
+        created: 0,
+        updated: 0,
+        expiry: 0,
+        ..Default::default()
+    };
+    put_space_key(&mut db, space, "bar".as_bytes(), vmeta, &Genesis::default())
+        .await
+        .unwrap();
+
+    let r_space = get_space_info(&db, space).await.unwrap().unwrap().raw_space;
+    assert_eq!(prune_expired_keys(&mut db, 10, 16).await.unwrap(), 0);
+    assert_eq!(prune_expired_keys(&mut db, 30, 16).await.unwrap(), 1);
+    assert!(!db
+        .has(&space_value_key(r_space, "foo".as_bytes()))
+        .await
+        .unwrap());
+    assert!(!db
+        .has(&prefix_ttl_key(10, r_space, "foo".as_bytes()))
+        .await
+        .unwrap());
+    assert!(!db
+        .has(&prefix_tx_value_key(&ids::Id::from_slice(&[1u8; ids::LEN])))
+        .await
+        .unwrap());
+    assert!(!db
+        .has(&prefix_ttl_key(20, r_space, "bar".as_bytes()))
+        .await
+        .unwrap());
+    assert!(get_value(&db, space, "bar".as_bytes(), 30)
+        .await
+        .unwrap()
+        .is_some());
+    assert_eq!(
+        get_space_info(&db, space).await.unwrap().unwrap().usage,
+        Usage {
+            keys: 2,
+            bytes: 12,
+            stored: 6
+        }
+    );
 }
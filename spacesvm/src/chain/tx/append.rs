@@ -0,0 +1,369 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chain::{
+        codec::{Packer, Unpacker},
+        storage::{self, prefix_tx_value_key, ValueMeta},
+        tx::decoder::{create_typed_data, mini_kvvm_domain, MessageValue, Type, TypedData},
+        validation,
+    },
+    genesis::Genesis,
+};
+
+use super::{
+    base,
+    decoder::{
+        TD_BLOCK_ID, TD_BYTES, TD_BYTES32, TD_KEY, TD_PRICE, TD_SPACE, TD_STRING, TD_UINT64,
+        TD_VALUE,
+    },
+    set,
+    tx::TransactionType,
+    unsigned,
+};
+
+/// Appends bytes to the value of a key, which is created if it is missing.
+/// Unlike a set, only the appended bytes are sent and concurrent appends do
+/// not overwrite each other. The whole value is stored again, so the bytes
+/// of the current value are charged on top of the appended ones.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Tx {
+    pub base_tx: base::Tx,
+
+    /// Space of the key.
+    pub space: String,
+
+    /// Key to append to.
+    pub key: String,
+
+    /// Bytes added to the end of the current value.
+    pub value: Vec<u8>,
+}
+
+impl Tx {
+    pub fn unpack(base_tx: base::Tx, unpacker: &mut Unpacker) -> Result<Self> {
+        Ok(Self {
+            base_tx,
+            space: unpacker.unpack_str()?,
+            key: unpacker.unpack_str()?,
+            value: unpacker.unpack_bytes()?,
+        })
+    }
+}
+
+// important to define an unique name of the trait implementation
+#[typetag::serde(name = "append")]
+#[tonic::async_trait]
+impl unsigned::Transaction for Tx {
+    async fn get_block_id(&self) -> avalanche_types::ids::Id {
+        self.base_tx.block_id
+    }
+
+    async fn set_block_id(&mut self, id: avalanche_types::ids::Id) {
+        self.base_tx.block_id = id;
+    }
+
+    async fn get_base_tx(&self) -> base::Tx {
+        self.base_tx
+    }
+
+    async fn set_base_tx(&mut self, base_tx: base::Tx) {
+        self.base_tx = base_tx;
+    }
+
+    async fn get_value(&self) -> Option<Vec<u8>> {
+        Some(self.value.clone())
+    }
+
+    async fn set_value(&mut self, value: Vec<u8>) -> Result<()> {
+        self.value = value;
+        Ok(())
+    }
+
+    async fn typ(&self) -> TransactionType {
+        TransactionType::Append
+    }
+
+    async fn fee_units(&self, _genesis: &Genesis) -> u64 {
+        0
+    }
+
+    async fn validate(&self, genesis: &Genesis) -> Result<()> {
+        validation::check_space(&self.space)?;
+        validation::check_key(&self.key)?;
+        validation::check_value(&self.value, genesis)
+    }
+
+    async fn pack(&self, packer: &mut Packer) {
+        packer.pack_str(&self.space);
+        packer.pack_str(&self.key);
+        packer.pack_bytes(&self.value);
+    }
+
+    async fn execute(&self, txn_ctx: unsigned::TransactionContext) -> Result<()> {
+        let genesis = txn_ctx.genesis.clone();
+        log::debug!(
+            "execute: append space: {} key: {} bytes: {}",
+            self.space,
+            self.key,
+            self.value.len()
+        );
+        let stored = update_value(&txn_ctx, &self.space, &self.key, "appends", |current| {
+            let mut value = current.unwrap_or_default();
+            value.extend_from_slice(&self.value);
+            validation::check_value(&value, &genesis)?;
+            Ok(value)
+        })
+        .await?;
+
+        // the fee only covers the appended bytes
+        let unit_size = genesis.value_unit_size.max(1);
+        let units = (stored as u64 / unit_size).saturating_sub(self.value.len() as u64 / unit_size);
+        self.base_tx.charge_units(&txn_ctx, units).await
+    }
+
+    async fn typed_data(&self) -> TypedData {
+        let mut tx_fields: Vec<Type> = Vec::new();
+        tx_fields.push(Type {
+            name: TD_SPACE.to_owned(),
+            type_: TD_STRING.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_KEY.to_owned(),
+            type_: TD_STRING.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_VALUE.to_owned(),
+            type_: TD_BYTES.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_BLOCK_ID.to_owned(),
+            type_: TD_BYTES32.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_PRICE.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+
+        let mut message: HashMap<String, MessageValue> = HashMap::with_capacity(5);
+        message.insert(
+            TD_SPACE.to_owned(),
+            MessageValue::String(self.space.clone()),
+        );
+        message.insert(TD_KEY.to_owned(), MessageValue::String(self.key.clone()));
+        message.insert(
+            TD_VALUE.to_owned(),
+            MessageValue::Bytes(self.value.to_vec()),
+        );
+        message.insert(
+            TD_BLOCK_ID.to_owned(),
+            MessageValue::Bytes(self.base_tx.block_id.to_vec()),
+        );
+        message.insert(TD_PRICE.to_owned(), MessageValue::Uint(self.base_tx.price));
+
+        return create_typed_data(
+            super::tx::TransactionType::Append,
+            tx_fields,
+            message,
+            mini_kvvm_domain(self.base_tx.magic, &self.base_tx.chain_id),
+        );
+    }
+}
+
+/// Replaces the value of [key] with the one [update] derives from the
+/// current value, None if the key is missing or expired. The key keeps its
+/// creation time, expiry and metadata while the new value is stored under
+/// the id of the tx. [action] names the writes in permission errors. Keys of
+/// the length of a value hash are only written by sets, which check that the
+/// key is the hash of the value. Returns the size of the new value.
+pub(super) async fn update_value<F>(
+    txn_ctx: &unsigned::TransactionContext,
+    space: &str,
+    key: &str,
+    action: &str,
+    update: F,
+) -> Result<usize>
+where
+    F: FnOnce(Option<Vec<u8>>) -> Result<Vec<u8>>,
+{
+    if key.len() == set::HASH_LEN {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} not allowed for content addressed key: {}", action, key),
+        ));
+    }
+
+    let mut db = txn_ctx.db.clone();
+
+    let info = storage::get_space_info(&db, space.as_bytes())
+        .await
+        .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+    if info.is_none() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("space not found: {}", space),
+        ));
+    }
+    let info = info.unwrap();
+    if info.is_expired(txn_ctx.block_time) {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("space expired: {}", space),
+        ));
+    }
+    let allowed = storage::can_write(
+        &db,
+        &info,
+        &txn_ctx.sender,
+        &txn_ctx.cosigners,
+        key.as_bytes(),
+        txn_ctx.block_time,
+    )
+    .await
+    .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+    if !allowed {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            format!(
+                "{} only allowed for space owner or grantees: {}",
+                action, space
+            ),
+        ));
+    }
+
+    let current =
+        storage::get_value_meta(&db, space.as_bytes(), key.as_bytes(), txn_ctx.block_time).await?;
+    let value = match &current {
+        None => update(None)?,
+        Some(_) => update(
+            storage::get_value(&db, space.as_bytes(), key.as_bytes(), txn_ctx.block_time).await?,
+        )?,
+    };
+
+    let vmeta = match current {
+        None => ValueMeta {
+            size: value.len() as u64,
+            tx_id: txn_ctx.tx_id,
+            created: txn_ctx.block_time,
+            updated: txn_ctx.block_time,
+            ..Default::default()
+        },
+        Some(current) => ValueMeta {
+            size: value.len() as u64,
+            tx_id: txn_ctx.tx_id,
+            updated: txn_ctx.block_time,
+            ..current
+        },
+    };
+    storage::put_space_key(
+        &mut db,
+        space.as_bytes(),
+        key.as_bytes(),
+        vmeta,
+        &txn_ctx.genesis,
+    )
+    .await
+    .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+
+    db.put(&prefix_tx_value_key(&txn_ctx.tx_id), &value)
+        .await
+        .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+    Ok(value.len())
+}
+
+#[tokio::test]
+async fn append_tx_test() {
+    use super::unsigned::Transaction;
+    use avalanche_types::ids;
+
+    let owner = ethereum_types::Address::zero();
+    let other = ethereum_types::Address::from_low_u64_be(1);
+    let db = avalanche_types::subnet::rpc::database::memdb::Database::new();
+    let genesis = Genesis {
+        max_value_size: 8,
+        value_unit_size: 2,
+        ..Default::default()
+    };
+    let ctx = |sender: ethereum_types::Address, block_time: u64| unsigned::TransactionContext {
+        genesis: genesis.clone(),
+        ..unsigned::TransactionContext::for_test(db.clone(), sender, block_time, block_time as u8)
+    };
+    let append = |value: &str| Tx {
+        base_tx: base::Tx {
+            price: 1,
+            ..Default::default()
+        },
+        space: "kvs".to_string(),
+        key: "log".to_string(),
+        value: value.as_bytes().to_vec(),
+    };
+
+    let resp = append("a").execute(ctx(owner, 1)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::NotFound);
+
+    let tx = crate::chain::tx::claim::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+    };
+    assert!(tx.execute(ctx(owner, 1)).await.is_ok());
+
+    // the first append creates the key, later ones pay for the bytes of the
+    // current value stored again
+    assert!(append("abc").execute(ctx(owner, 2)).await.is_ok());
+    storage::add_balance(&mut db.clone(), &owner, 2)
+        .await
+        .unwrap();
+    assert!(append("def").execute(ctx(owner, 3)).await.is_ok());
+    assert_eq!(storage::get_balance(&db, &owner).await.unwrap(), 0);
+    assert_eq!(
+        storage::get_value(&db, "kvs".as_bytes(), "log".as_bytes(), 3)
+            .await
+            .unwrap(),
+        Some("abcdef".as_bytes().to_vec())
+    );
+    let vmeta = storage::get_value_meta(&db, "kvs".as_bytes(), "log".as_bytes(), 3)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(vmeta.size, 6);
+    assert_eq!(vmeta.created, 2);
+    assert_eq!(vmeta.updated, 3);
+    assert_eq!(vmeta.tx_id, ids::Id::from_slice(&[3u8; ids::LEN]));
+
+    // the appended value must stay within the value size limit
+    let resp = append("ghi").execute(ctx(owner, 4)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    let resp = append("g").execute(ctx(other, 4)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    // content addressed keys can not be appended to
+    let key = set::value_hash("abc".as_bytes());
+    let tx = set::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        key: key.clone(),
+        value: "abc".as_bytes().to_vec(),
+        expiry: 0,
+        content_type: String::new(),
+        attributes: Default::default(),
+    };
+    assert!(tx.execute(ctx(owner, 5)).await.is_ok());
+    let resp = Tx {
+        key: key.clone(),
+        ..append("d")
+    }
+    .execute(ctx(owner, 6))
+    .await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert_eq!(
+        storage::get_value(&db, "kvs".as_bytes(), key.as_bytes(), 6)
+            .await
+            .unwrap(),
+        Some("abc".as_bytes().to_vec())
+    );
+}
@@ -3,12 +3,30 @@ use std::io::{Error, ErrorKind, Result};
 use avalanche_types::ids;
 use serde::{Deserialize, Serialize};
 
+use crate::chain::{
+    codec::{Packer, Unpacker},
+    storage,
+    vm::Context,
+};
+
 use super::unsigned;
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
 pub struct Tx {
     #[serde(deserialize_with = "ids::must_deserialize_id")]
     pub block_id: ids::Id,
+
+    /// Price paid for each unit consumed by the transaction.
+    #[serde(default)]
+    pub price: u64,
+
+    /// Network magic of the chain the transaction was signed for.
+    #[serde(default)]
+    pub magic: u64,
+
+    /// Id of the chain the transaction was signed for.
+    #[serde(default, deserialize_with = "ids::must_deserialize_id")]
+    pub chain_id: ids::Id,
 }
 
 impl Tx {
@@ -20,10 +38,81 @@ impl Tx {
         self.block_id = id;
     }
 
-    pub async fn execute_base(&self, _txn_ctx: unsigned::TransactionContext) -> Result<()> {
+    /// Packs the fields in the order of the Go SpacesVM BaseTx. The chain id
+    /// is bound through the signature and not encoded.
+    pub fn pack(&self, packer: &mut Packer) {
+        packer.pack_id(&self.block_id);
+        packer.pack_u64(self.magic);
+        packer.pack_u64(self.price);
+    }
+
+    pub fn unpack(unpacker: &mut Unpacker, chain_id: ids::Id) -> Result<Self> {
+        Ok(Self {
+            block_id: unpacker.unpack_id()?,
+            magic: unpacker.unpack_u64()?,
+            price: unpacker.unpack_u64()?,
+            chain_id,
+        })
+    }
+
+    pub async fn execute_base(
+        &self,
+        txn_ctx: &unsigned::TransactionContext,
+        context: &Context,
+    ) -> Result<()> {
         if self.block_id.is_empty() {
             return Err(Error::new(ErrorKind::Other, "invalid block id"));
         }
+        // txs expire once their block falls out of the lookback window
+        if !context.recent_block_ids.contains(&self.block_id) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("block id is not recent: {}", self.block_id),
+            ));
+        }
+        if context.recent_tx_ids.contains(&txn_ctx.tx_id) {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("duplicate tx: {}", txn_ctx.tx_id),
+            ));
+        }
+        // the domain of the signature must match this chain to prevent replays
+        if self.magic != txn_ctx.genesis.magic {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "invalid magic: {} (expected: {})",
+                    self.magic, txn_ctx.genesis.magic
+                ),
+            ));
+        }
+        if self.chain_id != txn_ctx.chain_id {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "invalid chain id: {} (expected: {})",
+                    self.chain_id, txn_ctx.chain_id
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Charges the sender [units] at the price of the tx in addition to the
+    /// fee paid up front, for work which depends on the state such as the
+    /// keys a tx removes. The units are burned like the fee.
+    pub async fn charge_units(
+        &self,
+        txn_ctx: &unsigned::TransactionContext,
+        units: u64,
+    ) -> Result<()> {
+        let fee = units.checked_mul(self.price).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("fee overflow: {}", txn_ctx.tx_id),
+            )
+        })?;
+        storage::sub_balance(&mut txn_ctx.db.clone(), &txn_ctx.sender, fee).await?;
         Ok(())
     }
 }
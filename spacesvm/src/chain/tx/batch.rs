@@ -0,0 +1,509 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+};
+
+use avalanche_types::{hash, ids, subnet::rpc::database::versiondb};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chain::{
+        codec::{Packer, Unpacker},
+        tx::decoder::{
+            create_typed_data, mini_kvvm_domain, MessageValue, Type, TypedData, TypedDataMessage,
+        },
+        validation,
+    },
+    genesis::Genesis,
+};
+
+use super::{
+    base,
+    decoder::{
+        TD_ACTION, TD_BLOCK_ID, TD_BYTES, TD_BYTES32, TD_KEY, TD_OP, TD_OPS, TD_PRICE, TD_SPACE,
+        TD_STRING, TD_UINT64, TD_VALUE,
+    },
+    delete, set,
+    tx::TransactionType,
+    unsigned,
+};
+
+/// Maximum number of ops in a single batch.
+pub const MAX_OPS: usize = 64;
+
+const OP_SET: u8 = 0;
+const OP_DELETE: u8 = 1;
+
+/// Single write of a batch.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum Op {
+    /// Create or update a key/value pair.
+    Set { key: String, value: Vec<u8> },
+    /// Remove a key.
+    Delete { key: String },
+}
+
+impl Op {
+    pub fn pack(&self, packer: &mut Packer) {
+        match self {
+            Op::Set { key, value } => {
+                packer.pack_u8(OP_SET);
+                packer.pack_str(key);
+                packer.pack_bytes(value);
+            }
+            Op::Delete { key } => {
+                packer.pack_u8(OP_DELETE);
+                packer.pack_str(key);
+            }
+        }
+    }
+
+    pub fn unpack(unpacker: &mut Unpacker) -> Result<Self> {
+        match unpacker.unpack_u8()? {
+            OP_SET => Ok(Op::Set {
+                key: unpacker.unpack_str()?,
+                value: unpacker.unpack_bytes()?,
+            }),
+            OP_DELETE => Ok(Op::Delete {
+                key: unpacker.unpack_str()?,
+            }),
+            op => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unknown batch op: {}", op),
+            )),
+        }
+    }
+
+    /// Checks the key and the value of the op.
+    pub fn validate(&self, genesis: &Genesis) -> Result<()> {
+        match self {
+            Op::Set { key, value } => {
+                validation::check_key(key)?;
+                validation::check_value(value, genesis)
+            }
+            Op::Delete { key } => validation::check_key(key),
+        }
+    }
+
+    /// Returns the typed data fields of an op.
+    pub fn typed_fields() -> Vec<Type> {
+        vec![
+            Type {
+                name: TD_ACTION.to_owned(),
+                type_: TD_STRING.to_owned(),
+            },
+            Type {
+                name: TD_KEY.to_owned(),
+                type_: TD_STRING.to_owned(),
+            },
+            Type {
+                name: TD_VALUE.to_owned(),
+                type_: TD_BYTES.to_owned(),
+            },
+        ]
+    }
+
+    /// Returns the typed data message of an op. The value of a delete is
+    /// empty.
+    pub fn typed_message(&self) -> TypedDataMessage {
+        let (action, key, value) = match self {
+            Op::Set { key, value } => ("set", key, value.clone()),
+            Op::Delete { key } => ("delete", key, vec![]),
+        };
+
+        let mut message: TypedDataMessage = HashMap::with_capacity(3);
+        message.insert(
+            TD_ACTION.to_owned(),
+            MessageValue::String(action.to_owned()),
+        );
+        message.insert(TD_KEY.to_owned(), MessageValue::String(key.clone()));
+        message.insert(TD_VALUE.to_owned(), MessageValue::Bytes(value));
+        message
+    }
+
+    /// Parses an op from its typed data message. The value of a delete must
+    /// be empty.
+    pub fn from_typed_message(message: &TypedDataMessage) -> Result<Self> {
+        let field = |name: &str| {
+            message
+                .get(name)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("op missing {:?}", name)))
+        };
+
+        let (action, key, value) = match (field(TD_ACTION)?, field(TD_KEY)?, field(TD_VALUE)?) {
+            (
+                MessageValue::String(action),
+                MessageValue::String(key),
+                MessageValue::Bytes(value),
+            ) => (action, key, value),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid op: {:?}", message),
+                ))
+            }
+        };
+        match action.as_str() {
+            "set" => Ok(Op::Set {
+                key: key.to_owned(),
+                value: value.to_owned(),
+            }),
+            "delete" if value.is_empty() => Ok(Op::Delete {
+                key: key.to_owned(),
+            }),
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid op: {:?}", message),
+            )),
+        }
+    }
+}
+
+/// Returns the id the value of the [index]th op of a batch is stored under.
+pub fn op_id(tx_id: &ids::Id, index: usize) -> ids::Id {
+    let concat = [&tx_id.to_vec()[..], &(index as u32).to_be_bytes()[..]].concat();
+    ids::Id::from_slice(hash::keccak256(concat).as_bytes())
+}
+
+/// Applies a list of set and delete ops to a single space. Either all ops
+/// are applied in order or none of them is.
+///
+/// Each value is stored under the id of its op, which the value meta of the
+/// key references as its tx id, see [crate::chain::storage].
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Tx {
+    pub base_tx: base::Tx,
+
+    /// Space all of the keys belong to.
+    pub space: String,
+
+    /// Ops applied in order.
+    pub ops: Vec<Op>,
+}
+
+impl Tx {
+    pub fn unpack(base_tx: base::Tx, unpacker: &mut Unpacker) -> Result<Self> {
+        let space = unpacker.unpack_str()?;
+        let count = unpacker.unpack_u32()? as usize;
+        if count > MAX_OPS {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("too many batch ops: {} (max: {})", count, MAX_OPS),
+            ));
+        }
+        let mut ops = Vec::with_capacity(count);
+        for _ in 0..count {
+            ops.push(Op::unpack(unpacker)?);
+        }
+
+        Ok(Self {
+            base_tx,
+            space,
+            ops,
+        })
+    }
+}
+
+// important to define an unique name of the trait implementation
+#[typetag::serde(name = "batch")]
+#[tonic::async_trait]
+impl unsigned::Transaction for Tx {
+    async fn get_block_id(&self) -> avalanche_types::ids::Id {
+        self.base_tx.block_id
+    }
+
+    async fn set_block_id(&mut self, id: avalanche_types::ids::Id) {
+        self.base_tx.block_id = id;
+    }
+
+    async fn get_base_tx(&self) -> base::Tx {
+        self.base_tx
+    }
+
+    async fn set_base_tx(&mut self, base_tx: base::Tx) {
+        self.base_tx = base_tx;
+    }
+
+    async fn get_value(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    async fn set_value(&mut self, _value: Vec<u8>) -> std::io::Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "value is not supported for batch tx",
+        ))
+    }
+
+    async fn typ(&self) -> TransactionType {
+        TransactionType::Batch
+    }
+
+    /// Every op after the first pays the base units and the values are
+    /// charged like the value of a set tx.
+    async fn fee_units(&self, genesis: &Genesis) -> u64 {
+        let unit_size = genesis.value_unit_size.max(1);
+        let ops = self.ops.len().saturating_sub(1) as u64;
+        self.ops.iter().fold(
+            ops.saturating_mul(genesis.base_tx_units),
+            |units, op| match op {
+                Op::Set { value, .. } => units.saturating_add(value.len() as u64 / unit_size),
+                Op::Delete { .. } => units,
+            },
+        )
+    }
+
+    async fn validate(&self, genesis: &Genesis) -> Result<()> {
+        validation::check_space(&self.space)?;
+        for op in self.ops.iter() {
+            op.validate(genesis)?;
+        }
+        Ok(())
+    }
+
+    async fn pack(&self, packer: &mut Packer) {
+        packer.pack_str(&self.space);
+        packer.pack_u32(self.ops.len() as u32);
+        for op in self.ops.iter() {
+            op.pack(packer);
+        }
+    }
+
+    async fn execute(&self, txn_ctx: unsigned::TransactionContext) -> Result<()> {
+        if self.ops.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "batch must contain at least one op",
+            ));
+        }
+        if self.ops.len() > MAX_OPS {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("too many batch ops: {} (max: {})", self.ops.len(), MAX_OPS),
+            ));
+        }
+
+        // ops are written to an overlay which is only committed once all of
+        // them succeeded
+        let vdb = versiondb::Database::new(txn_ctx.db.clone());
+        for (i, op) in self.ops.iter().enumerate() {
+            let op_id = op_id(&txn_ctx.tx_id, i);
+            let op_ctx = unsigned::TransactionContext {
+                db: Box::new(vdb.clone()),
+                block_time: txn_ctx.block_time,
+                tx_id: op_id,
+                sender: txn_ctx.sender,
+                cosigners: txn_ctx.cosigners.clone(),
+                genesis: txn_ctx.genesis.clone(),
+                chain_id: txn_ctx.chain_id,
+            };
+
+            log::debug!("execute: batch space: {} op {}: {:?}", self.space, i, op);
+            let resp = match op {
+                Op::Set { key, value } => {
+                    let tx = set::Tx {
+                        base_tx: self.base_tx,
+                        space: self.space.clone(),
+                        key: key.clone(),
+                        value: value.clone(),
+                        expiry: 0,
+                        content_type: String::new(),
+                        attributes: Default::default(),
+                    };
+                    unsigned::Transaction::execute(&tx, op_ctx).await
+                }
+                Op::Delete { key } => {
+                    let tx = delete::Tx {
+                        base_tx: self.base_tx,
+                        space: self.space.clone(),
+                        key: key.clone(),
+                    };
+                    unsigned::Transaction::execute(&tx, op_ctx).await
+                }
+            };
+            resp.map_err(|e| Error::new(e.kind(), format!("batch op {} failed: {}", i, e)))?;
+        }
+
+        vdb.commit()
+            .await
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))
+    }
+
+    async fn typed_data(&self) -> TypedData {
+        let mut tx_fields: Vec<Type> = Vec::new();
+        tx_fields.push(Type {
+            name: TD_SPACE.to_owned(),
+            type_: TD_STRING.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_OPS.to_owned(),
+            type_: format!("{}[]", TD_OP),
+        });
+        tx_fields.push(Type {
+            name: TD_BLOCK_ID.to_owned(),
+            type_: TD_BYTES32.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_PRICE.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+
+        let mut message: HashMap<String, MessageValue> = HashMap::with_capacity(4);
+        message.insert(
+            TD_SPACE.to_owned(),
+            MessageValue::String(self.space.clone()),
+        );
+        message.insert(
+            TD_OPS.to_owned(),
+            MessageValue::Array(
+                self.ops
+                    .iter()
+                    .map(|op| MessageValue::Struct(op.typed_message()))
+                    .collect(),
+            ),
+        );
+        message.insert(
+            TD_BLOCK_ID.to_owned(),
+            MessageValue::Bytes(self.base_tx.block_id.to_vec()),
+        );
+        message.insert(TD_PRICE.to_owned(), MessageValue::Uint(self.base_tx.price));
+
+        let mut typed_data = create_typed_data(
+            super::tx::TransactionType::Batch,
+            tx_fields,
+            message,
+            mini_kvvm_domain(self.base_tx.magic, &self.base_tx.chain_id),
+        );
+        typed_data
+            .types
+            .insert(TD_OP.to_owned(), Op::typed_fields());
+        typed_data
+    }
+}
+
+#[tokio::test]
+async fn batch_tx_test() {
+    use super::unsigned::Transaction;
+    use crate::chain::storage;
+
+    let owner = ethereum_types::Address::zero();
+    let db = avalanche_types::subnet::rpc::database::memdb::Database::new();
+    let ctx = |block_time: u64, tx_id: u8| {
+        unsigned::TransactionContext::for_test(db.clone(), owner, block_time, tx_id)
+    };
+
+    let tx = crate::chain::tx::claim::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+    };
+    assert!(tx.execute(ctx(0, 0)).await.is_ok());
+
+    // index and data keys are written together
+    let tx = Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        ops: vec![
+            Op::Set {
+                key: "a".to_string(),
+                value: "1".as_bytes().to_vec(),
+            },
+            Op::Set {
+                key: "index".to_string(),
+                value: "a".as_bytes().to_vec(),
+            },
+        ],
+    };
+    assert!(tx.execute(ctx(1, 1)).await.is_ok());
+    assert_eq!(
+        storage::get_value(&db, "kvs".as_bytes(), "index".as_bytes(), 0)
+            .await
+            .unwrap()
+            .unwrap(),
+        "a".as_bytes()
+    );
+    let vmeta = storage::get_value_meta(&db, "kvs".as_bytes(), "a".as_bytes(), 0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(vmeta.tx_id, op_id(&ids::Id::from_slice(&[1; ids::LEN]), 0));
+
+    // a failing op leaves all keys untouched
+    let tx = Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        ops: vec![
+            Op::Set {
+                key: "index".to_string(),
+                value: "b".as_bytes().to_vec(),
+            },
+            Op::Delete {
+                key: "a".to_string(),
+            },
+            Op::Delete {
+                key: "missing".to_string(),
+            },
+        ],
+    };
+    assert!(tx.execute(ctx(2, 2)).await.is_err());
+    assert_eq!(
+        storage::get_value(&db, "kvs".as_bytes(), "index".as_bytes(), 0)
+            .await
+            .unwrap()
+            .unwrap(),
+        "a".as_bytes()
+    );
+    assert!(
+        storage::get_value_meta(&db, "kvs".as_bytes(), "a".as_bytes(), 0)
+            .await
+            .unwrap()
+            .is_some()
+    );
+
+    // only the owner may write
+    let mut other = ctx(3, 3);
+    other.sender = ethereum_types::Address::from_low_u64_be(1);
+    let resp = tx.execute(other).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    // empty batches are rejected
+    let tx = Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        ops: vec![],
+    };
+    let resp = tx.execute(ctx(4, 4)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    // typed data and the binary encoding round-trip
+    let tx = Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        ops: vec![
+            Op::Set {
+                key: "a".to_string(),
+                value: vec![0x00, 0xff],
+            },
+            Op::Delete {
+                key: "b".to_string(),
+            },
+        ],
+    };
+    let typed_data = tx.typed_data().await;
+    let json = serde_json::to_string(&typed_data).unwrap();
+    let parsed: TypedData = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        crate::chain::tx::decoder::hash_structured_data(&parsed).unwrap(),
+        crate::chain::tx::decoder::hash_structured_data(&typed_data).unwrap()
+    );
+    let parsed = parsed.parse_typed_data().unwrap();
+    let mut expected = Packer::new();
+    tx.pack(&mut expected).await;
+    let mut actual = Packer::new();
+    parsed.pack(&mut actual).await;
+    let bytes = actual.take_bytes().unwrap();
+    assert_eq!(bytes, expected.take_bytes().unwrap());
+
+    let unpacked = Tx::unpack(base::Tx::default(), &mut Unpacker::new(&bytes)).unwrap();
+    assert_eq!(unpacked.ops, tx.ops);
+}
@@ -0,0 +1,326 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind, Result},
+};
+
+use avalanche_types::ids;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chain::{
+        codec::{Packer, Unpacker},
+        storage::get_value_meta,
+        tx::decoder::{create_typed_data, mini_kvvm_domain, MessageValue, Type, TypedData},
+        validation,
+    },
+    genesis::Genesis,
+};
+
+use super::{
+    base,
+    batch::Op,
+    decoder::{TD_BLOCK_ID, TD_BYTES32, TD_EXPECTED, TD_PRICE, TD_SPACE, TD_STRING, TD_UINT64},
+    delete, set,
+    tx::TransactionType,
+    unsigned,
+};
+
+/// Sets or deletes a key only if its value meta still references the
+/// [expected] tx id. An empty id requires the key to not exist, so writers
+/// sharing a space do not overwrite each other's updates.
+///
+/// The value is stored under the id of the tx, see [crate::chain::storage].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Tx {
+    pub base_tx: base::Tx,
+
+    /// Base namespace for the key value pair.
+    pub space: String,
+
+    /// Tx id of the current value meta of the key. Empty if the key must not
+    /// exist.
+    pub expected: ids::Id,
+
+    /// Write applied once the current value meta matches.
+    pub op: Op,
+}
+
+impl Tx {
+    pub fn unpack(base_tx: base::Tx, unpacker: &mut Unpacker) -> Result<Self> {
+        Ok(Self {
+            base_tx,
+            space: unpacker.unpack_str()?,
+            expected: unpacker.unpack_id()?,
+            op: Op::unpack(unpacker)?,
+        })
+    }
+}
+
+/// Returned as the inner error of an ErrorKind::InvalidInput io::Error when
+/// the current value meta of a key does not reference the expected tx id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreconditionFailed {
+    pub key: String,
+    pub current: ids::Id,
+    pub expected: ids::Id,
+}
+
+impl fmt::Display for PreconditionFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "precondition failed: key {} references {} (expected: {})",
+            self.key, self.current, self.expected
+        )
+    }
+}
+
+impl std::error::Error for PreconditionFailed {}
+
+/// Returns true if the io::Error wraps a [PreconditionFailed] error.
+pub fn is_precondition_failed(error: &Error) -> bool {
+    error
+        .get_ref()
+        .map_or(false, |e| e.is::<PreconditionFailed>())
+}
+
+// important to define an unique name of the trait implementation
+#[typetag::serde(name = "cas")]
+#[tonic::async_trait]
+impl unsigned::Transaction for Tx {
+    async fn get_block_id(&self) -> avalanche_types::ids::Id {
+        self.base_tx.block_id
+    }
+
+    async fn set_block_id(&mut self, id: avalanche_types::ids::Id) {
+        self.base_tx.block_id = id;
+    }
+
+    async fn get_base_tx(&self) -> base::Tx {
+        self.base_tx
+    }
+
+    async fn set_base_tx(&mut self, base_tx: base::Tx) {
+        self.base_tx = base_tx;
+    }
+
+    async fn get_value(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    async fn set_value(&mut self, _value: Vec<u8>) -> std::io::Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "value is not supported for cas tx",
+        ))
+    }
+
+    async fn typ(&self) -> TransactionType {
+        TransactionType::Cas
+    }
+
+    /// The value is charged like the value of a set tx.
+    async fn fee_units(&self, genesis: &Genesis) -> u64 {
+        match &self.op {
+            Op::Set { value, .. } => value.len() as u64 / genesis.value_unit_size.max(1),
+            Op::Delete { .. } => 0,
+        }
+    }
+
+    async fn validate(&self, genesis: &Genesis) -> Result<()> {
+        validation::check_space(&self.space)?;
+        self.op.validate(genesis)
+    }
+
+    async fn pack(&self, packer: &mut Packer) {
+        packer.pack_str(&self.space);
+        packer.pack_id(&self.expected);
+        self.op.pack(packer);
+    }
+
+    async fn execute(&self, txn_ctx: unsigned::TransactionContext) -> Result<()> {
+        let db = txn_ctx.db.clone();
+        let key = match &self.op {
+            Op::Set { key, .. } => key,
+            Op::Delete { key } => key,
+        };
+
+        let current = get_value_meta(
+            &db,
+            self.space.as_bytes(),
+            key.as_bytes(),
+            txn_ctx.block_time,
+        )
+        .await
+        .map_err(|e| Error::new(ErrorKind::Other, format!("failed to get value meta: {}", e)))?
+        .map(|vmeta| vmeta.tx_id)
+        .unwrap_or_else(ids::Id::empty);
+        if current != self.expected {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                PreconditionFailed {
+                    key: key.clone(),
+                    current,
+                    expected: self.expected,
+                },
+            ));
+        }
+
+        log::debug!(
+            "execute: cas space: {} expected: {} op: {:?}",
+            self.space,
+            self.expected,
+            self.op
+        );
+        match &self.op {
+            Op::Set { key, value } => {
+                let tx = set::Tx {
+                    base_tx: self.base_tx,
+                    space: self.space.clone(),
+                    key: key.clone(),
+                    value: value.clone(),
+                    expiry: 0,
+                    content_type: String::new(),
+                    attributes: Default::default(),
+                };
+                unsigned::Transaction::execute(&tx, txn_ctx).await
+            }
+            Op::Delete { key } => {
+                let tx = delete::Tx {
+                    base_tx: self.base_tx,
+                    space: self.space.clone(),
+                    key: key.clone(),
+                };
+                unsigned::Transaction::execute(&tx, txn_ctx).await
+            }
+        }
+    }
+
+    async fn typed_data(&self) -> TypedData {
+        let mut tx_fields: Vec<Type> = Vec::new();
+        tx_fields.push(Type {
+            name: TD_SPACE.to_owned(),
+            type_: TD_STRING.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_EXPECTED.to_owned(),
+            type_: TD_BYTES32.to_owned(),
+        });
+        tx_fields.extend(Op::typed_fields());
+        tx_fields.push(Type {
+            name: TD_BLOCK_ID.to_owned(),
+            type_: TD_BYTES32.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_PRICE.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+
+        let mut message = self.op.typed_message();
+        message.insert(
+            TD_SPACE.to_owned(),
+            MessageValue::String(self.space.clone()),
+        );
+        message.insert(
+            TD_EXPECTED.to_owned(),
+            MessageValue::Bytes(self.expected.to_vec()),
+        );
+        message.insert(
+            TD_BLOCK_ID.to_owned(),
+            MessageValue::Bytes(self.base_tx.block_id.to_vec()),
+        );
+        message.insert(TD_PRICE.to_owned(), MessageValue::Uint(self.base_tx.price));
+
+        return create_typed_data(
+            super::tx::TransactionType::Cas,
+            tx_fields,
+            message,
+            mini_kvvm_domain(self.base_tx.magic, &self.base_tx.chain_id),
+        );
+    }
+}
+
+#[tokio::test]
+async fn cas_tx_test() {
+    use super::unsigned::Transaction;
+    use crate::chain::storage;
+
+    let owner = ethereum_types::Address::zero();
+    let db = avalanche_types::subnet::rpc::database::memdb::Database::new();
+    let ctx = |tx_id: u8| unsigned::TransactionContext::for_test(db.clone(), owner, 1, tx_id);
+    let cas_set = |expected: ids::Id, value: &str| Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        expected,
+        op: Op::Set {
+            key: "foo".to_string(),
+            value: value.as_bytes().to_vec(),
+        },
+    };
+
+    let tx = crate::chain::tx::claim::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+    };
+    assert!(tx.execute(ctx(0)).await.is_ok());
+
+    // the key must not exist yet
+    assert!(cas_set(ids::Id::empty(), "a").execute(ctx(1)).await.is_ok());
+    let resp = cas_set(ids::Id::empty(), "b").execute(ctx(2)).await;
+    assert!(is_precondition_failed(&resp.unwrap_err()));
+    assert_eq!(
+        storage::get_value(&db, "kvs".as_bytes(), "foo".as_bytes(), 0)
+            .await
+            .unwrap()
+            .unwrap(),
+        "a".as_bytes()
+    );
+
+    // writers which saw the current value win, stale ones fail
+    let first = ids::Id::from_slice(&[1; ids::LEN]);
+    assert!(cas_set(first, "c").execute(ctx(3)).await.is_ok());
+    let resp = cas_set(first, "d").execute(ctx(4)).await.unwrap_err();
+    assert_eq!(resp.kind(), ErrorKind::InvalidInput);
+    assert!(is_precondition_failed(&resp));
+    let vmeta = storage::get_value_meta(&db, "kvs".as_bytes(), "foo".as_bytes(), 0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(vmeta.tx_id, ids::Id::from_slice(&[3; ids::LEN]));
+
+    // other failures are not reported as precondition failures
+    let mut other = ctx(5);
+    other.sender = ethereum_types::Address::from_low_u64_be(1);
+    let resp = cas_set(vmeta.tx_id, "e").execute(other).await.unwrap_err();
+    assert_eq!(resp.kind(), ErrorKind::PermissionDenied);
+    assert!(!is_precondition_failed(&resp));
+    let resp = Error::new(ErrorKind::InvalidInput, "precondition failed");
+    assert!(!is_precondition_failed(&resp));
+
+    let tx = Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        expected: vmeta.tx_id,
+        op: Op::Delete {
+            key: "foo".to_string(),
+        },
+    };
+    assert!(tx.execute(ctx(6)).await.is_ok());
+    assert!(
+        storage::get_value_meta(&db, "kvs".as_bytes(), "foo".as_bytes(), 0)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // typed data round-trips
+    let typed_data = tx.typed_data().await;
+    let json = serde_json::to_string(&typed_data).unwrap();
+    let parsed: TypedData = serde_json::from_str(&json).unwrap();
+    let parsed = parsed.parse_typed_data().unwrap();
+    let mut expected = Packer::new();
+    tx.pack(&mut expected).await;
+    let mut actual = Packer::new();
+    parsed.pack(&mut actual).await;
+    assert_eq!(actual.take_bytes().unwrap(), expected.take_bytes().unwrap());
+}
@@ -6,14 +6,19 @@ use std::{
 use avalanche_types::ids;
 use serde::{Deserialize, Serialize};
 
-use crate::chain::{
-    storage::{has_space, put_space_info},
-    tx::decoder::{create_typed_data, MessageValue, Type, TypedData},
+use crate::{
+    chain::{
+        codec::{Packer, Unpacker},
+        storage::{get_space_info, put_space_info},
+        tx::decoder::{create_typed_data, mini_kvvm_domain, MessageValue, Type, TypedData},
+        validation,
+    },
+    genesis::Genesis,
 };
 
 use super::{
     base,
-    decoder::{TD_BLOCK_ID, TD_SPACE, TD_STRING},
+    decoder::{TD_BLOCK_ID, TD_BYTES32, TD_PRICE, TD_SPACE, TD_STRING, TD_UINT64},
     tx::TransactionType,
     unsigned,
 };
@@ -23,10 +28,85 @@ pub struct Info {
     pub created: u64,
     pub updated: u64,
 
+    /// Unix timestamp after which the space can no longer be used and may
+    /// be claimed again.
+    pub expiry: u64,
+
     #[serde(deserialize_with = "ids::short::must_deserialize_id")]
     pub raw_space: ids::short::Id,
 
     pub owner: ethereum_types::H160,
+
+    /// Keys stored in the space and the bytes they use.
+    #[serde(default)]
+    pub usage: Usage,
+
+    /// Set of addresses which own the space instead of [owner].
+    #[serde(default)]
+    pub multisig: Option<Multisig>,
+
+    /// Number of times the space was moved or its multisig changed. Grants
+    /// only apply while it stays the same as when they were given.
+    #[serde(default)]
+    pub owner_epoch: u64,
+}
+
+/// Storage used by a space. Every key counts its own length and the size of
+/// its value.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub keys: u64,
+    pub bytes: u64,
+
+    /// Bytes of the values of the keys, which are released together with
+    /// the values when the keys are overwritten, deleted or expire.
+    #[serde(default)]
+    pub stored: u64,
+}
+
+/// M-of-N owner of a space. A tx is authorized once at least [threshold]
+/// distinct members signed it, either as its sender or as cosigners.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Multisig {
+    pub threshold: u64,
+    pub members: Vec<ethereum_types::Address>,
+}
+
+impl Multisig {
+    /// Returns true if enough members are among the signers of a tx.
+    pub fn is_met(
+        &self,
+        sender: &ethereum_types::Address,
+        cosigners: &[ethereum_types::Address],
+    ) -> bool {
+        let signed = self
+            .members
+            .iter()
+            .filter(|m| *m == sender || cosigners.contains(m))
+            .count();
+        signed as u64 >= self.threshold
+    }
+}
+
+impl Info {
+    /// Returns true if the space expired before the given block time.
+    pub fn is_expired(&self, block_time: u64) -> bool {
+        self.expiry < block_time
+    }
+
+    /// Returns true if a tx signed by [sender] and [cosigners] acts as the
+    /// owner of the space. Spaces with a multisig are only owned by enough
+    /// of its members, not by [owner].
+    pub fn is_owned_by(
+        &self,
+        sender: &ethereum_types::Address,
+        cosigners: &[ethereum_types::Address],
+    ) -> bool {
+        match &self.multisig {
+            Some(multisig) => multisig.is_met(sender, cosigners),
+            None => &self.owner == sender,
+        }
+    }
 }
 
 /// Creates a space, which acts as a logical key-space root.
@@ -36,6 +116,15 @@ pub struct Tx {
     pub space: String,
 }
 
+impl Tx {
+    pub fn unpack(base_tx: base::Tx, unpacker: &mut Unpacker) -> Result<Self> {
+        Ok(Self {
+            base_tx,
+            space: unpacker.unpack_str()?,
+        })
+    }
+}
+
 // important to define an unique name of the trait implementation
 #[typetag::serde(name = "claim")]
 #[tonic::async_trait]
@@ -48,6 +137,14 @@ impl unsigned::Transaction for Tx {
         self.base_tx.block_id = id;
     }
 
+    async fn get_base_tx(&self) -> base::Tx {
+        self.base_tx
+    }
+
+    async fn set_base_tx(&mut self, base_tx: base::Tx) {
+        self.base_tx = base_tx;
+    }
+
     async fn get_value(&self) -> Option<Vec<u8>> {
         None
     }
@@ -60,27 +157,48 @@ impl unsigned::Transaction for Tx {
     }
 
     async fn typ(&self) -> TransactionType {
-        TransactionType::Set
+        TransactionType::Claim
+    }
+
+    async fn fee_units(&self, genesis: &Genesis) -> u64 {
+        genesis.claim_units
+    }
+
+    async fn validate(&self, genesis: &Genesis) -> Result<()> {
+        validation::check_claim(&self.space, genesis)
+    }
+
+    async fn pack(&self, packer: &mut Packer) {
+        packer.pack_str(&self.space);
     }
 
     async fn execute(&self, txn_ctx: unsigned::TransactionContext) -> Result<()> {
         let mut db = txn_ctx.db;
-        // TODO: ensure expected format of space
-
-        // ensure space does not exist for now update requires an explicit delete tx
-        if has_space(&db, self.space.as_bytes()).await? {
-            log::debug!("execute: space exists: {}", self.space);
-            return Err(Error::new(
-                ErrorKind::AlreadyExists,
-                format!("space exists: {}", self.space),
-            ));
+
+        // ensure space does not exist, a live space must be released with a
+        // delete space tx before it can be claimed again
+        if let Some(info) = get_space_info(&db, self.space.as_bytes()).await? {
+            if !info.is_expired(txn_ctx.block_time) {
+                log::debug!("execute: space exists: {}", self.space);
+                return Err(Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!("space exists: {}", self.space),
+                ));
+            }
+            // the keys of the expired space are left under its old raw space
+            // until they are removed.
+            log::debug!("execute: reclaiming expired space: {}", self.space);
         }
         log::debug!("execute: space exec sender: {}", &txn_ctx.sender);
         let new_info = Info {
             created: txn_ctx.block_time,
             updated: txn_ctx.block_time,
+            expiry: txn_ctx.block_time + txn_ctx.genesis.claim_reward,
             owner: txn_ctx.sender,
             raw_space: ids::short::Id::empty(),
+            usage: Usage::default(),
+            multisig: None,
+            owner_epoch: 0,
         };
 
         return put_space_info(&mut db, self.space.as_bytes(), new_info, 0).await;
@@ -94,23 +212,29 @@ impl unsigned::Transaction for Tx {
         });
         tx_fields.push(Type {
             name: TD_BLOCK_ID.to_owned(),
-            type_: TD_STRING.to_owned(),
+            type_: TD_BYTES32.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_PRICE.to_owned(),
+            type_: TD_UINT64.to_owned(),
         });
 
-        let mut message: HashMap<String, MessageValue> = HashMap::with_capacity(1);
+        let mut message: HashMap<String, MessageValue> = HashMap::with_capacity(3);
         message.insert(
             TD_SPACE.to_owned(),
-            MessageValue::Vec(self.space.as_bytes().to_vec()),
+            MessageValue::String(self.space.clone()),
         );
-        let value = MessageValue::Vec(self.base_tx.block_id.to_vec());
-        log::debug!("typed_data: message value: {:?}", value);
-        log::debug!("typed_data: id vec: {:?}", self.base_tx.block_id.to_vec());
-        log::debug!("typed_data: id: {}", self.base_tx.block_id);
         message.insert(
             TD_BLOCK_ID.to_owned(),
-            MessageValue::Vec(self.base_tx.block_id.to_vec()),
+            MessageValue::Bytes(self.base_tx.block_id.to_vec()),
         );
+        message.insert(TD_PRICE.to_owned(), MessageValue::Uint(self.base_tx.price));
 
-        return create_typed_data(super::tx::TransactionType::Claim, tx_fields, message);
+        return create_typed_data(
+            super::tx::TransactionType::Claim,
+            tx_fields,
+            message,
+            mini_kvvm_domain(self.base_tx.magic, &self.base_tx.chain_id),
+        );
     }
 }
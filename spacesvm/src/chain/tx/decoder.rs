@@ -1,23 +1,46 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     io::{Error, ErrorKind, Result},
+    str::FromStr,
 };
 
 use avalanche_types::{hash, ids};
 use eip_712::Type as ParserType;
 use ethereum_types::H256;
-use serde::{de, Deserialize, Serialize};
+use serde::{Deserialize, Serialize};
 use serde_json::to_value;
 
-use super::{base, claim, delete, set, tx::TransactionType, unsigned};
+use super::{
+    append, base, batch, cas, claim, delete, delete_space, grant, increment, lifeline, move_space,
+    multisig, revoke, set, transfer, tx::TransactionType, unsigned,
+};
 
 pub const TD_STRING: &str = "string";
-pub const TD_U64: &str = "u64";
+pub const TD_UINT64: &str = "uint64";
+pub const TD_ADDRESS: &str = "address";
 pub const TD_BYTES: &str = "bytes";
+pub const TD_BYTES32: &str = "bytes32";
 pub const TD_BLOCK_ID: &str = "blockId";
 pub const TD_SPACE: &str = "space";
 pub const TD_KEY: &str = "key";
 pub const TD_VALUE: &str = "value";
+pub const TD_TO: &str = "to";
+pub const TD_UNITS: &str = "units";
+pub const TD_PRICE: &str = "price";
+pub const TD_OPS: &str = "ops";
+pub const TD_OP: &str = "op";
+pub const TD_ACTION: &str = "action";
+pub const TD_EXPECTED: &str = "expected";
+pub const TD_PREFIX: &str = "prefix";
+pub const TD_EXPIRY: &str = "expiry";
+pub const TD_THRESHOLD: &str = "threshold";
+pub const TD_MEMBERS: &str = "members";
+pub const TD_CONTENT_TYPE: &str = "contentType";
+pub const TD_ATTRIBUTES: &str = "attributes";
+pub const TD_ATTRIBUTE: &str = "attribute";
+pub const TD_NAME: &str = "name";
+pub const TD_AMOUNT: &str = "amount";
+pub const TD_COSIGNERS: &str = "cosigners";
 
 pub type Type = eip_712::FieldType;
 
@@ -30,108 +53,228 @@ pub type TypedDataMessage = HashMap<String, MessageValue>;
 pub struct TypedDataDomain {
     pub name: String,
     pub magic: String,
+    #[serde(rename = "blockchainId")]
+    pub blockchain_id: String,
 }
 
-pub fn mini_kvvm_domain(_m: u64) -> TypedDataDomain {
+/// Returns the domain which binds a signature to the network [magic] and
+/// the blockchain it was signed for.
+pub fn mini_kvvm_domain(magic: u64, chain_id: &ids::Id) -> TypedDataDomain {
     TypedDataDomain {
         name: "SpacesVm".to_string(),
-        magic: "0x00".to_string(), // radix(m, 10).to_string(),
+        magic: format!("0x{:x}", magic),
+        blockchain_id: format!("0x{}", hex::encode(chain_id.to_vec())),
     }
 }
 
-#[derive(Debug, Clone)]
-pub enum MessageValue {
-    // TODO:combine?
-    Vec(Vec<u8>),
-    Bytes(Vec<u8>),
-}
-
-impl MessageValue {
-    pub fn to_string(self) -> String {
-        match self {
-            MessageValue::Vec(v) => String::from_utf8_lossy(&v).to_string(),
-            MessageValue::Bytes(v) => String::from_utf8_lossy(&v).to_string(),
-        }
+impl TypedDataDomain {
+    /// Returns the network magic of the domain.
+    pub fn get_magic(&self) -> Result<u64> {
+        u64::from_str_radix(self.magic.trim_start_matches("0x"), 16)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("invalid magic: {}", e)))
     }
-    pub fn to_vec(self) -> Vec<u8> {
-        match self {
-            MessageValue::Vec(v) => v,
-            MessageValue::Bytes(v) => v,
+
+    /// Returns the blockchain id of the domain.
+    pub fn get_blockchain_id(&self) -> Result<ids::Id> {
+        let id = hex::decode(self.blockchain_id.trim_start_matches("0x")).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid blockchain id: {}", e),
+            )
+        })?;
+        if id.len() != ids::LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid blockchain id length: {}", id.len()),
+            ));
         }
+        Ok(ids::Id::from_slice(&id))
     }
 }
 
+/// Value of a typed data message field in the JSON form accepted by
+/// "eth_signTypedData_v4".
+#[derive(Debug, Clone)]
+pub enum MessageValue {
+    /// Value of a "string" field.
+    String(String),
+    /// Value of a "uint64" field, a 0x-prefixed hex quantity.
+    Uint(u64),
+    /// Value of an "address", "bytes" or "bytes32" field, 0x-prefixed hex.
+    Bytes(Vec<u8>),
+    /// Value of an array field such as "op[]".
+    Array(Vec<MessageValue>),
+    /// Value of a field declared with one of the struct types.
+    Struct(TypedDataMessage),
+}
+
 impl Serialize for MessageValue {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
         match self {
-            MessageValue::Vec(v) => serializer.serialize_str(&hex::encode(v)),
+            MessageValue::String(v) => serializer.serialize_str(v),
+            MessageValue::Uint(v) => serializer.serialize_str(&format!("0x{:x}", v)),
             MessageValue::Bytes(v) => {
                 serializer.serialize_str(format!("0x{}", &hex::encode(v)).as_str())
             }
+            MessageValue::Array(v) => v.serialize(serializer),
+            MessageValue::Struct(v) => v.serialize(serializer),
         }
     }
 }
 
-impl<'de> Deserialize<'de> for MessageValue {
-    fn deserialize<D: de::Deserializer<'de>>(
-        deserializer: D,
-    ) -> std::result::Result<Self, D::Error> {
-        struct MessageValueVisitor;
-        impl<'de> de::Visitor<'de> for MessageValueVisitor {
-            type Value = MessageValue;
-
-            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                write!(formatter, "a potential or array of potentials")
-            }
-
-            fn visit_string<E: de::Error>(self, v: String) -> std::result::Result<Self::Value, E> {
-                if v.starts_with("0x") {
-                    match hex::decode(&v[2..]) {
-                        Ok(s) => Ok(MessageValue::Bytes(s)),
-                        Err(e) => Err(E::custom(e.to_string())),
-                    }
-                } else {
-                    match hex::decode(v) {
-                        Ok(s) => Ok(MessageValue::Vec(s)),
-                        Err(e) => Err(E::custom(e.to_string())),
-                    }
+impl MessageValue {
+    /// Decodes the JSON value of a field declared with the EIP-712 type [typ].
+    /// Struct types are looked up in [types].
+    pub fn from_json(typ: &str, value: &serde_json::Value, types: &Types) -> Result<Self> {
+        if let Some(inner) = typ.strip_suffix("[]") {
+            return match value {
+                serde_json::Value::Array(v) => v
+                    .iter()
+                    .map(|v| MessageValue::from_json(inner, v, types))
+                    .collect::<Result<Vec<_>>>()
+                    .map(MessageValue::Array),
+                _ => Err(invalid_value(typ, value)),
+            };
+        }
+        if let Some(fields) = types.get(typ) {
+            return match value {
+                serde_json::Value::Object(v) => {
+                    decode_struct(fields, v.iter(), types).map(MessageValue::Struct)
                 }
-            }
+                _ => Err(invalid_value(typ, value)),
+            };
+        }
 
-            fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Self::Value, E> {
-                if v.starts_with("0x") {
-                    match hex::decode(&v[2..]) {
-                        Ok(s) => Ok(MessageValue::Bytes(s)),
-                        Err(e) => Err(E::custom(e.to_string())),
-                    }
-                } else {
-                    match hex::decode(v) {
-                        Ok(s) => Ok(MessageValue::Vec(s)),
-                        Err(e) => Err(E::custom(e.to_string())),
-                    }
-                }
+        match (typ, value) {
+            (TD_STRING, serde_json::Value::String(v)) => Ok(MessageValue::String(v.to_owned())),
+            (TD_UINT64, serde_json::Value::Number(v)) => v
+                .as_u64()
+                .map(MessageValue::Uint)
+                .ok_or_else(|| invalid_value(typ, value)),
+            (TD_UINT64, serde_json::Value::String(v)) => {
+                let parsed = match v.strip_prefix("0x") {
+                    Some(hex) => u64::from_str_radix(hex, 16),
+                    None => v.parse::<u64>(),
+                };
+                parsed
+                    .map(MessageValue::Uint)
+                    .map_err(|_| invalid_value(typ, value))
             }
+            (TD_ADDRESS, serde_json::Value::String(v)) => decode_hex(v, Some(20))
+                .map(MessageValue::Bytes)
+                .ok_or_else(|| invalid_value(typ, value)),
+            (TD_BYTES32, serde_json::Value::String(v)) => decode_hex(v, Some(32))
+                .map(MessageValue::Bytes)
+                .ok_or_else(|| invalid_value(typ, value)),
+            (TD_BYTES, serde_json::Value::String(v)) => decode_hex(v, None)
+                .map(MessageValue::Bytes)
+                .ok_or_else(|| invalid_value(typ, value)),
+            _ => Err(invalid_value(typ, value)),
         }
+    }
+}
+
+/// Decodes a 0x-prefixed hex string of the expected length.
+fn decode_hex(v: &str, len: Option<usize>) -> Option<Vec<u8>> {
+    let b = hex::decode(v.strip_prefix("0x")?).ok()?;
+    match len {
+        Some(len) if b.len() != len => None,
+        _ => Some(b),
+    }
+}
 
-        deserializer.deserialize_any(MessageValueVisitor)
+/// Decodes the fields of a struct, all of which must be declared in [fields].
+fn decode_struct<'a>(
+    fields: &[Type],
+    values: impl Iterator<Item = (&'a String, &'a serde_json::Value)>,
+    types: &Types,
+) -> Result<TypedDataMessage> {
+    let mut message = TypedDataMessage::new();
+    for (key, value) in values {
+        let field = fields.iter().find(|f| &f.name == key).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("typed data key not declared: {:?}", key),
+            )
+        })?;
+        message.insert(
+            key.to_owned(),
+            MessageValue::from_json(&field.type_, value, types)?,
+        );
     }
+    Ok(message)
+}
+
+fn invalid_value(typ: &str, value: &serde_json::Value) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid {} value: {}", typ, value),
+    )
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(try_from = "RawTypedData")]
 pub struct TypedData {
     pub types: Types,
+    #[serde(rename = "primaryType", serialize_with = "serialize_primary_type")]
     pub primary_type: TransactionType,
     pub domain: TypedDataDomain,
     pub message: TypedDataMessage,
 }
 
+/// Typed data as received over JSON. The message is decoded once the declared
+/// types of its fields are known.
+#[derive(Deserialize)]
+struct RawTypedData {
+    types: Types,
+    #[serde(rename = "primaryType")]
+    primary_type: String,
+    domain: TypedDataDomain,
+    message: HashMap<String, serde_json::Value>,
+}
+
+impl TryFrom<RawTypedData> for TypedData {
+    type Error = Error;
+
+    fn try_from(raw: RawTypedData) -> Result<Self> {
+        let primary_type = TransactionType::from_str(&raw.primary_type)?;
+        let fields = raw.types.get(&raw.primary_type).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("types missing primary type: {}", raw.primary_type),
+            )
+        })?;
+
+        let message = decode_struct(fields, raw.message.iter(), &raw.types)?;
+
+        Ok(TypedData {
+            types: raw.types,
+            primary_type,
+            domain: raw.domain,
+            message,
+        })
+    }
+}
+
+/// Serializes the primary type as the name of its entry in the types.
+fn serialize_primary_type<S>(
+    typ: &TransactionType,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(&typ.to_string())
+}
+
 pub fn create_typed_data(
     tx_type: TransactionType,
     tx_fields: Vec<Type>,
     message: TypedDataMessage,
+    domain: TypedDataDomain,
 ) -> TypedData {
     let mut types = Types::new();
     types.insert(tx_type.to_string(), tx_fields);
@@ -146,12 +289,16 @@ pub fn create_typed_data(
                 name: "magic".to_owned(),
                 type_: "uint64".to_owned(),
             },
+            Type {
+                name: "blockchainId".to_owned(),
+                type_: "bytes32".to_owned(),
+            },
         ],
     );
     return TypedData {
         types,
         message,
-        domain: mini_kvvm_domain(0), // TODO: pass magic
+        domain,
         primary_type: tx_type,
     };
 }
@@ -159,13 +306,26 @@ pub fn create_typed_data(
 impl TypedData {
     // Attempts to return the base tx from typed data.
     pub fn parse_base_tx(&self) -> Result<base::Tx> {
-        let r_block_id = self
-            .get_typed_message_vec(TD_BLOCK_ID.to_owned())
-            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
-
+        let r_block_id = self.get_typed_message_vec(TD_BLOCK_ID)?;
+        if r_block_id.len() != ids::LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid block id length: {}", r_block_id.len()),
+            ));
+        }
         let block_id = ids::Id::from_slice(&r_block_id);
 
-        Ok(base::Tx { block_id })
+        let price = self.get_typed_message_u64(TD_PRICE)?;
+
+        let magic = self.domain.get_magic()?;
+        let chain_id = self.domain.get_blockchain_id()?;
+
+        Ok(base::Tx {
+            block_id,
+            price,
+            magic,
+            chain_id,
+        })
     }
 
     // Attempts to return and unsigned transaction from typed data.
@@ -179,41 +339,210 @@ impl TypedData {
 
         match self.primary_type {
             TransactionType::Claim => {
-                let space = self
-                    .get_typed_message(TD_SPACE.to_owned())
-                    .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+                let space = self.get_typed_message(TD_SPACE)?;
                 Ok(Box::new(claim::Tx { base_tx, space }))
             }
 
             TransactionType::Set => {
-                let space = self
-                    .get_typed_message(TD_SPACE.to_owned())
-                    .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
-                let key = self
-                    .get_typed_message(TD_KEY.to_owned())
-                    .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
-                let value = self
-                    .get_typed_message(TD_VALUE.to_owned())
-                    .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+                let space = self.get_typed_message(TD_SPACE)?;
+                let key = self.get_typed_message(TD_KEY)?;
+                let value = self.get_typed_message_vec(TD_VALUE)?;
+                // the expiry is only declared by sets which expire
+                let expiry = match self.message.get(TD_EXPIRY) {
+                    None => 0,
+                    Some(_) => match self.get_typed_message_u64(TD_EXPIRY)? {
+                        0 => {
+                            return Err(Error::new(
+                                ErrorKind::InvalidData,
+                                "set expiry must not be zero",
+                            ))
+                        }
+                        expiry => expiry,
+                    },
+                };
+                let content_type = match self.message.get(TD_CONTENT_TYPE) {
+                    None => String::new(),
+                    Some(_) => match self.get_typed_message(TD_CONTENT_TYPE)? {
+                        content_type if content_type.is_empty() => {
+                            return Err(Error::new(
+                                ErrorKind::InvalidData,
+                                "set content type must not be empty",
+                            ))
+                        }
+                        content_type => content_type,
+                    },
+                };
+                let mut attributes = BTreeMap::new();
+                if self.message.contains_key(TD_ATTRIBUTES) {
+                    let values = self.get_typed_message_array(TD_ATTRIBUTES)?;
+                    if values.is_empty() {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            "set attributes must not be empty",
+                        ));
+                    }
+                    // attributes are signed in the order they are packed in
+                    for attribute in values.iter() {
+                        let (name, value) = match attribute {
+                            MessageValue::Struct(v) => match (v.get(TD_NAME), v.get(TD_VALUE)) {
+                                (
+                                    Some(MessageValue::String(name)),
+                                    Some(MessageValue::String(value)),
+                                ) => (name, value),
+                                _ => {
+                                    return Err(Error::new(
+                                        ErrorKind::InvalidData,
+                                        format!("invalid attribute: {:?}", attribute),
+                                    ))
+                                }
+                            },
+                            _ => {
+                                return Err(Error::new(
+                                    ErrorKind::InvalidData,
+                                    format!("attribute is not a struct: {:?}", attribute),
+                                ))
+                            }
+                        };
+                        if attributes.keys().next_back() >= Some(name) {
+                            return Err(Error::new(
+                                ErrorKind::InvalidData,
+                                format!("attribute out of order: {:?}", name),
+                            ));
+                        }
+                        attributes.insert(name.to_owned(), value.to_owned());
+                    }
+                }
                 Ok(Box::new(set::Tx {
                     base_tx,
                     space,
-                    key: key,
-                    value: value.as_bytes().to_vec(),
+                    key,
+                    value,
+                    expiry,
+                    content_type,
+                    attributes,
                 }))
             }
 
             TransactionType::Delete => {
-                let space = self
-                    .get_typed_message(TD_SPACE.to_owned())
-                    .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
-                let key = self
-                    .get_typed_message(TD_KEY.to_owned())
-                    .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+                let space = self.get_typed_message(TD_SPACE)?;
+                let key = self.get_typed_message(TD_KEY)?;
                 Ok(Box::new(delete::Tx {
                     base_tx,
                     space,
-                    key: key,
+                    key,
+                }))
+            }
+
+            TransactionType::Transfer => {
+                let to = self.get_typed_message_address(TD_TO)?;
+                let units = self.get_typed_message_u64(TD_UNITS)?;
+                Ok(Box::new(transfer::Tx { base_tx, to, units }))
+            }
+
+            TransactionType::Move => {
+                let space = self.get_typed_message(TD_SPACE)?;
+                let to = self.get_typed_message_address(TD_TO)?;
+                Ok(Box::new(move_space::Tx { base_tx, space, to }))
+            }
+
+            TransactionType::Lifeline => {
+                let space = self.get_typed_message(TD_SPACE)?;
+                let units = self.get_typed_message_u64(TD_UNITS)?;
+                Ok(Box::new(lifeline::Tx {
+                    base_tx,
+                    space,
+                    units,
+                }))
+            }
+
+            TransactionType::Batch => {
+                let space = self.get_typed_message(TD_SPACE)?;
+                let ops = self
+                    .get_typed_message_array(TD_OPS)?
+                    .iter()
+                    .map(|op| match op {
+                        MessageValue::Struct(op) => batch::Op::from_typed_message(op),
+                        op => Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!("batch op is not a struct: {:?}", op),
+                        )),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Box::new(batch::Tx {
+                    base_tx,
+                    space,
+                    ops,
+                }))
+            }
+            TransactionType::Cas => {
+                let space = self.get_typed_message(TD_SPACE)?;
+                let expected = self.get_typed_message_vec(TD_EXPECTED)?;
+                if expected.len() != ids::LEN {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("invalid expected tx id length: {}", expected.len()),
+                    ));
+                }
+                Ok(Box::new(cas::Tx {
+                    base_tx,
+                    space,
+                    expected: ids::Id::from_slice(&expected),
+                    op: batch::Op::from_typed_message(&self.message)?,
+                }))
+            }
+            TransactionType::Grant => {
+                let space = self.get_typed_message(TD_SPACE)?;
+                let to = self.get_typed_message_address(TD_TO)?;
+                let prefix = self.get_typed_message(TD_PREFIX)?;
+                let expiry = self.get_typed_message_u64(TD_EXPIRY)?;
+                Ok(Box::new(grant::Tx {
+                    base_tx,
+                    space,
+                    to,
+                    prefix,
+                    expiry,
+                }))
+            }
+            TransactionType::Revoke => {
+                let space = self.get_typed_message(TD_SPACE)?;
+                let to = self.get_typed_message_address(TD_TO)?;
+                Ok(Box::new(revoke::Tx { base_tx, space, to }))
+            }
+            TransactionType::Multisig => {
+                let space = self.get_typed_message(TD_SPACE)?;
+                let threshold = self.get_typed_message_u64(TD_THRESHOLD)?;
+                let members = self.get_typed_message_addresses(TD_MEMBERS)?;
+                Ok(Box::new(multisig::Tx {
+                    base_tx,
+                    space,
+                    threshold,
+                    members,
+                }))
+            }
+            TransactionType::DeleteSpace => {
+                let space = self.get_typed_message(TD_SPACE)?;
+                Ok(Box::new(delete_space::Tx { base_tx, space }))
+            }
+            TransactionType::Append => {
+                let space = self.get_typed_message(TD_SPACE)?;
+                let key = self.get_typed_message(TD_KEY)?;
+                let value = self.get_typed_message_vec(TD_VALUE)?;
+                Ok(Box::new(append::Tx {
+                    base_tx,
+                    space,
+                    key,
+                    value,
+                }))
+            }
+            TransactionType::Increment => {
+                let space = self.get_typed_message(TD_SPACE)?;
+                let key = self.get_typed_message(TD_KEY)?;
+                let amount = self.get_typed_message_u64(TD_AMOUNT)?;
+                Ok(Box::new(increment::Tx {
+                    base_tx,
+                    space,
+                    key,
+                    amount,
                 }))
             }
             TransactionType::Unknown => Err(Error::new(
@@ -223,25 +552,130 @@ impl TypedData {
         }
     }
 
-    pub fn get_typed_message(&self, key: String) -> Result<String> {
-        match self.message.get(&key) {
-            Some(value) => Ok(value.to_owned().to_string()),
-            None => Err(Error::new(
+    fn get_message_value(&self, key: &str) -> Result<&MessageValue> {
+        self.message.get(key).ok_or_else(|| {
+            Error::new(
                 ErrorKind::NotFound,
                 format!("typed data key missing: {:?}", key),
+            )
+        })
+    }
+
+    /// Returns the value of a "string" field.
+    pub fn get_typed_message(&self, key: &str) -> Result<String> {
+        match self.get_message_value(key)? {
+            MessageValue::String(v) => Ok(v.to_owned()),
+            v => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("typed data key {:?} is not a string: {:?}", key, v),
             )),
         }
     }
 
-    pub fn get_typed_message_vec(&self, key: String) -> Result<Vec<u8>> {
-        match self.message.get(&key) {
-            Some(value) => Ok(value.to_owned().to_vec()),
-            None => Err(Error::new(
-                ErrorKind::NotFound,
-                format!("typed data key missing: {:?}", key),
+    /// Returns the value of a "bytes" or "bytes32" field.
+    pub fn get_typed_message_vec(&self, key: &str) -> Result<Vec<u8>> {
+        match self.get_message_value(key)? {
+            MessageValue::Bytes(v) => Ok(v.to_owned()),
+            v => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("typed data key {:?} is not bytes: {:?}", key, v),
             )),
         }
     }
+
+    /// Returns the value of a "uint64" field.
+    pub fn get_typed_message_u64(&self, key: &str) -> Result<u64> {
+        match self.get_message_value(key)? {
+            MessageValue::Uint(v) => Ok(*v),
+            v => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("typed data key {:?} is not a uint64: {:?}", key, v),
+            )),
+        }
+    }
+
+    /// Returns the elements of an array field.
+    pub fn get_typed_message_array(&self, key: &str) -> Result<&Vec<MessageValue>> {
+        match self.get_message_value(key)? {
+            MessageValue::Array(v) => Ok(v),
+            v => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("typed data key {:?} is not an array: {:?}", key, v),
+            )),
+        }
+    }
+
+    /// Returns the elements of an "address[]" field.
+    pub fn get_typed_message_addresses(&self, key: &str) -> Result<Vec<ethereum_types::Address>> {
+        self.get_typed_message_array(key)?
+            .iter()
+            .map(|v| match v {
+                MessageValue::Bytes(v) if v.len() == 20 => {
+                    Ok(ethereum_types::Address::from_slice(v))
+                }
+                v => Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("typed data key {:?} is not an address: {:?}", key, v),
+                )),
+            })
+            .collect()
+    }
+
+    /// Returns the typed data with the [cosigners] of a tx in an additional
+    /// "cosigners" field of the primary type, which the sender and every
+    /// cosigner sign. Typed data is returned as is if there are none.
+    pub fn with_cosigners(mut self, cosigners: &[ethereum_types::Address]) -> Self {
+        if cosigners.is_empty() {
+            return self;
+        }
+        if let Some(fields) = self.types.get_mut(&self.primary_type.to_string()) {
+            if !fields.iter().any(|field| field.name == TD_COSIGNERS) {
+                fields.push(Type {
+                    name: TD_COSIGNERS.to_owned(),
+                    type_: format!("{}[]", TD_ADDRESS),
+                });
+            }
+        }
+        self.message.insert(
+            TD_COSIGNERS.to_owned(),
+            MessageValue::Array(
+                cosigners
+                    .iter()
+                    .map(|c| MessageValue::Bytes(c.as_bytes().to_vec()))
+                    .collect(),
+            ),
+        );
+        self
+    }
+
+    /// Returns the cosigners declared by the typed data, empty if it has no
+    /// "cosigners" field.
+    pub fn parse_cosigners(&self) -> Result<Vec<ethereum_types::Address>> {
+        if !self.message.contains_key(TD_COSIGNERS) {
+            return Ok(vec![]);
+        }
+        let cosigners = self.get_typed_message_addresses(TD_COSIGNERS)?;
+        if cosigners.is_empty() {
+            // typed data without cosigners has no such field
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "typed data declares empty cosigners",
+            ));
+        }
+        Ok(cosigners)
+    }
+
+    /// Returns the value of an "address" field.
+    pub fn get_typed_message_address(&self, key: &str) -> Result<ethereum_types::Address> {
+        let v = self.get_typed_message_vec(key)?;
+        if v.len() != 20 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("typed data key {:?} is not an address", key),
+            ));
+        }
+        Ok(ethereum_types::Address::from_slice(&v))
+    }
 }
 
 pub fn hash_structured_data(typed_data: &TypedData) -> Result<H256> {
@@ -282,6 +716,7 @@ async fn signature_recovers() {
         space: "kvs".to_string(),
         key: String::new(),
         value: vec![],
+        ..Default::default()
     };
     let resp = tx_data.decode();
     assert!(resp.is_ok());
@@ -299,6 +734,7 @@ async fn signature_recovers() {
         space: "kvs".to_string(),
         key: "foo".to_string(),
         value: "bar".as_bytes().to_vec(),
+        ..Default::default()
     };
     let resp = tx_data.decode();
     assert!(resp.is_ok());
@@ -314,3 +750,145 @@ async fn signature_recovers() {
     assert_eq!(public_key.to_string(), sender.to_string());
     assert_eq!(public_key, sender,);
 }
+
+#[tokio::test]
+async fn typed_data_vectors() {
+    // digests were computed independently following EIP-712 and match the
+    // ones produced by "eth_signTypedData_v4" for the same JSON
+    let base_tx = base::Tx {
+        block_id: ids::Id::from_slice(&[1u8; ids::LEN]),
+        price: 2,
+        magic: 1,
+        chain_id: ids::Id::from_slice(&[4u8; ids::LEN]),
+    };
+    let block_id = format!("0x{}", "01".repeat(32));
+
+    let utx = claim::Tx {
+        base_tx,
+        space: "kvs".to_string(),
+    };
+    let typed_data = unsigned::Transaction::typed_data(&utx).await;
+    assert_eq!(
+        serde_json::to_value(&typed_data).unwrap(),
+        serde_json::json!({
+            "types": {
+                "EIP712Domain": [
+                    {"name": "name", "type": "string"},
+                    {"name": "magic", "type": "uint64"},
+                    {"name": "blockchainId", "type": "bytes32"},
+                ],
+                "claim": [
+                    {"name": "space", "type": "string"},
+                    {"name": "blockId", "type": "bytes32"},
+                    {"name": "price", "type": "uint64"},
+                ],
+            },
+            "primaryType": "claim",
+            "domain": {
+                "name": "SpacesVm",
+                "magic": "0x1",
+                "blockchainId": format!("0x{}", "04".repeat(32)),
+            },
+            "message": {
+                "space": "kvs",
+                "blockId": block_id,
+                "price": "0x2",
+            },
+        })
+    );
+    assert_eq!(
+        hex::encode(hash_structured_data(&typed_data).unwrap().as_bytes()),
+        "7556867adb13c6003983f173662e9ddd455cad8d5fa588c24cc7c1a0c2b4df65"
+    );
+
+    let utx = set::Tx {
+        base_tx,
+        space: "kvs".to_string(),
+        key: "foo".to_string(),
+        value: vec![0x00, 0xff, 0x62],
+        expiry: 0,
+        content_type: String::new(),
+        attributes: Default::default(),
+    };
+    let typed_data = unsigned::Transaction::typed_data(&utx).await;
+    assert_eq!(
+        hex::encode(hash_structured_data(&typed_data).unwrap().as_bytes()),
+        "b0881695dd603fcb01595e72deaf1756caa8e07b99713f0be977ddbef60767c6"
+    );
+
+    // wallet JSON parses back into the same tx
+    let json = serde_json::to_string(&typed_data).unwrap();
+    let parsed: TypedData = serde_json::from_str(&json).unwrap();
+    let parsed = parsed.parse_typed_data().unwrap();
+    assert_eq!(parsed.get_value().await.unwrap(), vec![0x00, 0xff, 0x62]);
+    assert_eq!(parsed.get_base_tx().await.price, 2);
+    assert_eq!(parsed.get_base_tx().await.magic, 1);
+
+    let utx = transfer::Tx {
+        base_tx,
+        to: ethereum_types::Address::from_low_u64_be(1),
+        units: 5,
+    };
+    let typed_data = unsigned::Transaction::typed_data(&utx).await;
+    assert_eq!(
+        hex::encode(hash_structured_data(&typed_data).unwrap().as_bytes()),
+        "ddb6d664d748c657d36be8a86710b2f2f57df06f734e1328bc7fdee513d1800e"
+    );
+}
+
+#[tokio::test]
+async fn typed_data_schema() {
+    use super::unsigned::Transaction;
+
+    let base_tx = base::Tx {
+        block_id: ids::Id::from_slice(&[1u8; ids::LEN]),
+        price: 2,
+        magic: 1,
+        chain_id: ids::Id::from_slice(&[4u8; ids::LEN]),
+    };
+
+    // strings which look like hex and non UTF-8 values round-trip exactly
+    let value = vec![0xc3, 0x28, 0x00, 0xff];
+    let utx = set::Tx {
+        base_tx,
+        space: "0xbeef".to_string(),
+        key: "cafe".to_string(),
+        value: value.clone(),
+        expiry: 0,
+        content_type: String::new(),
+        attributes: Default::default(),
+    };
+    let typed_data = utx.typed_data().await;
+    let json = serde_json::to_string(&typed_data).unwrap();
+    let parsed: TypedData = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        hash_structured_data(&parsed).unwrap(),
+        hash_structured_data(&typed_data).unwrap()
+    );
+    let parsed = parsed.parse_typed_data().unwrap();
+    let mut expected = crate::chain::codec::Packer::new();
+    utx.pack(&mut expected).await;
+    let mut actual = crate::chain::codec::Packer::new();
+    parsed.pack(&mut actual).await;
+    assert_eq!(actual.take_bytes().unwrap(), expected.take_bytes().unwrap());
+    assert_eq!(parsed.get_value().await.unwrap(), value);
+
+    // values must match their declared type
+    let mut message = serde_json::to_value(&typed_data).unwrap();
+    message["message"]["price"] = serde_json::json!("kvs");
+    assert!(serde_json::from_value::<TypedData>(message).is_err());
+
+    let mut message = serde_json::to_value(&typed_data).unwrap();
+    message["message"]["blockId"] = serde_json::json!("0x01");
+    assert!(serde_json::from_value::<TypedData>(message).is_err());
+
+    let mut message = serde_json::to_value(&typed_data).unwrap();
+    message["message"]["extra"] = serde_json::json!("0x01");
+    assert!(serde_json::from_value::<TypedData>(message).is_err());
+
+    // wallets may send quantities as numbers
+    let mut message = serde_json::to_value(&typed_data).unwrap();
+    message["message"]["price"] = serde_json::json!(3);
+    let parsed: TypedData = serde_json::from_value(message).unwrap();
+    assert_eq!(parsed.parse_base_tx().unwrap().price, 3);
+}
@@ -5,14 +5,19 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
-use crate::chain::{
-    storage,
-    tx::decoder::{create_typed_data, MessageValue, Type, TypedData},
+use crate::{
+    chain::{
+        codec::{Packer, Unpacker},
+        storage,
+        tx::decoder::{create_typed_data, mini_kvvm_domain, MessageValue, Type, TypedData},
+        validation,
+    },
+    genesis::Genesis,
 };
 
 use super::{
     base,
-    decoder::{TD_BLOCK_ID, TD_KEY, TD_SPACE, TD_STRING},
+    decoder::{TD_BLOCK_ID, TD_BYTES32, TD_KEY, TD_PRICE, TD_SPACE, TD_STRING, TD_UINT64},
     tx::TransactionType,
     unsigned,
 };
@@ -27,6 +32,16 @@ pub struct Tx {
     pub key: String,
 }
 
+impl Tx {
+    pub fn unpack(base_tx: base::Tx, unpacker: &mut Unpacker) -> Result<Self> {
+        Ok(Self {
+            base_tx,
+            space: unpacker.unpack_str()?,
+            key: unpacker.unpack_str()?,
+        })
+    }
+}
+
 // important to define an unique name of the trait implementation
 #[typetag::serde(name = "delete")]
 #[tonic::async_trait]
@@ -39,6 +54,14 @@ impl unsigned::Transaction for Tx {
         self.base_tx.block_id = id
     }
 
+    async fn get_base_tx(&self) -> base::Tx {
+        self.base_tx
+    }
+
+    async fn set_base_tx(&mut self, base_tx: base::Tx) {
+        self.base_tx = base_tx;
+    }
+
     async fn get_value(&self) -> Option<Vec<u8>> {
         None
     }
@@ -54,6 +77,20 @@ impl unsigned::Transaction for Tx {
         TransactionType::Delete
     }
 
+    async fn fee_units(&self, _genesis: &Genesis) -> u64 {
+        0
+    }
+
+    async fn validate(&self, _genesis: &Genesis) -> Result<()> {
+        validation::check_space(&self.space)?;
+        validation::check_key(&self.key)
+    }
+
+    async fn pack(&self, packer: &mut Packer) {
+        packer.pack_str(&self.space);
+        packer.pack_str(&self.key);
+    }
+
     async fn execute(&self, mut txn_ctx: unsigned::TransactionContext) -> Result<()> {
         let db = txn_ctx.db.clone();
 
@@ -67,19 +104,41 @@ impl unsigned::Transaction for Tx {
             ));
         }
         let info = info.unwrap();
-        if info.owner != txn_ctx.sender {
+        if info.is_expired(txn_ctx.block_time) {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("space expired: {}", self.space),
+            ));
+        }
+        let allowed = storage::can_write(
+            &db,
+            &info,
+            &txn_ctx.sender,
+            &txn_ctx.cosigners,
+            self.key.as_bytes(),
+            txn_ctx.block_time,
+        )
+        .await
+        .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+        if !allowed {
             return Err(Error::new(
                 ErrorKind::PermissionDenied,
-                format!("sets only allowed for space owner: {}", self.space),
+                format!(
+                    "deletes only allowed for space owner or grantees: {}",
+                    self.space
+                ),
             ));
         }
 
         // while we do not use value meta currently we verify it exists.
-        let v = storage::get_value_meta(&db, self.space.as_bytes(), self.key.as_bytes())
-            .await
-            .map_err(|e| {
-                Error::new(ErrorKind::Other, format!("failed to get value meta: {}", e))
-            })?;
+        let v = storage::get_value_meta(
+            &db,
+            self.space.as_bytes(),
+            self.key.as_bytes(),
+            txn_ctx.block_time,
+        )
+        .await
+        .map_err(|e| Error::new(ErrorKind::Other, format!("failed to get value meta: {}", e)))?;
 
         if v.is_none() {
             return Err(Error::new(ErrorKind::Other, "key is missing"));
@@ -99,28 +158,35 @@ impl unsigned::Transaction for Tx {
             type_: TD_STRING.to_owned(),
         });
         tx_fields.push(Type {
-            name: TD_BLOCK_ID.to_owned(),
+            name: TD_KEY.to_owned(),
             type_: TD_STRING.to_owned(),
         });
         tx_fields.push(Type {
-            name: TD_KEY.to_owned(),
-            type_: TD_STRING.to_owned(),
+            name: TD_BLOCK_ID.to_owned(),
+            type_: TD_BYTES32.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_PRICE.to_owned(),
+            type_: TD_UINT64.to_owned(),
         });
 
-        let mut message: HashMap<String, MessageValue> = HashMap::with_capacity(1);
+        let mut message: HashMap<String, MessageValue> = HashMap::with_capacity(4);
         message.insert(
             TD_SPACE.to_owned(),
-            MessageValue::Vec(self.space.as_bytes().to_vec()),
-        );
-        message.insert(
-            TD_KEY.to_owned(),
-            MessageValue::Vec(self.key.as_bytes().to_vec()),
+            MessageValue::String(self.space.clone()),
         );
+        message.insert(TD_KEY.to_owned(), MessageValue::String(self.key.clone()));
         message.insert(
             TD_BLOCK_ID.to_owned(),
-            MessageValue::Vec(self.base_tx.block_id.to_vec()),
+            MessageValue::Bytes(self.base_tx.block_id.to_vec()),
         );
+        message.insert(TD_PRICE.to_owned(), MessageValue::Uint(self.base_tx.price));
 
-        return create_typed_data(super::tx::TransactionType::Delete, tx_fields, message);
+        return create_typed_data(
+            super::tx::TransactionType::Delete,
+            tx_fields,
+            message,
+            mini_kvvm_domain(self.base_tx.magic, &self.base_tx.chain_id),
+        );
     }
 }
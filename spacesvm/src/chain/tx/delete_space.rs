@@ -0,0 +1,234 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chain::{
+        codec::{Packer, Unpacker},
+        storage::{self, get_space_info},
+        tx::decoder::{create_typed_data, mini_kvvm_domain, MessageValue, Type, TypedData},
+        validation,
+    },
+    genesis::Genesis,
+};
+
+use super::{
+    base,
+    decoder::{TD_BLOCK_ID, TD_BYTES32, TD_PRICE, TD_SPACE, TD_STRING, TD_UINT64},
+    tx::TransactionType,
+    unsigned,
+};
+
+/// Gives up a space before it expires. The space info, its keys and grants
+/// are removed right away so the name can be claimed again. Every key and
+/// grant removed is charged [Genesis::base_tx_units] like a delete tx.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Tx {
+    pub base_tx: base::Tx,
+
+    /// Space to release.
+    pub space: String,
+}
+
+impl Tx {
+    pub fn unpack(base_tx: base::Tx, unpacker: &mut Unpacker) -> Result<Self> {
+        Ok(Self {
+            base_tx,
+            space: unpacker.unpack_str()?,
+        })
+    }
+}
+
+// important to define an unique name of the trait implementation
+#[typetag::serde(name = "delete_space")]
+#[tonic::async_trait]
+impl unsigned::Transaction for Tx {
+    async fn get_block_id(&self) -> avalanche_types::ids::Id {
+        self.base_tx.block_id
+    }
+
+    async fn set_block_id(&mut self, id: avalanche_types::ids::Id) {
+        self.base_tx.block_id = id;
+    }
+
+    async fn get_base_tx(&self) -> base::Tx {
+        self.base_tx
+    }
+
+    async fn set_base_tx(&mut self, base_tx: base::Tx) {
+        self.base_tx = base_tx;
+    }
+
+    async fn get_value(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    async fn set_value(&mut self, _value: Vec<u8>) -> std::io::Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "value is not supported for delete space tx",
+        ))
+    }
+
+    async fn typ(&self) -> TransactionType {
+        TransactionType::DeleteSpace
+    }
+
+    async fn fee_units(&self, _genesis: &Genesis) -> u64 {
+        0
+    }
+
+    async fn validate(&self, _genesis: &Genesis) -> Result<()> {
+        validation::check_space(&self.space)
+    }
+
+    async fn pack(&self, packer: &mut Packer) {
+        packer.pack_str(&self.space);
+    }
+
+    async fn execute(&self, txn_ctx: unsigned::TransactionContext) -> Result<()> {
+        let mut db = txn_ctx.db.clone();
+
+        let info = get_space_info(&db, self.space.as_bytes())
+            .await
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+        if info.is_none() {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("space not found: {}", self.space),
+            ));
+        }
+        let info = info.unwrap();
+        if info.is_expired(txn_ctx.block_time) {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("space expired: {}", self.space),
+            ));
+        }
+        if !info.is_owned_by(&txn_ctx.sender, &txn_ctx.cosigners) {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("deletes only allowed for space owner: {}", self.space),
+            ));
+        }
+
+        let removed = storage::delete_space(&mut db, self.space.as_bytes(), &info)
+            .await
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+        log::debug!("execute: delete space: {} entries: {}", self.space, removed);
+
+        let units = (removed as u64).saturating_mul(txn_ctx.genesis.base_tx_units);
+        self.base_tx.charge_units(&txn_ctx, units).await
+    }
+
+    async fn typed_data(&self) -> TypedData {
+        let mut tx_fields: Vec<Type> = Vec::new();
+        tx_fields.push(Type {
+            name: TD_SPACE.to_owned(),
+            type_: TD_STRING.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_BLOCK_ID.to_owned(),
+            type_: TD_BYTES32.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_PRICE.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+
+        let mut message: HashMap<String, MessageValue> = HashMap::with_capacity(3);
+        message.insert(
+            TD_SPACE.to_owned(),
+            MessageValue::String(self.space.clone()),
+        );
+        message.insert(
+            TD_BLOCK_ID.to_owned(),
+            MessageValue::Bytes(self.base_tx.block_id.to_vec()),
+        );
+        message.insert(TD_PRICE.to_owned(), MessageValue::Uint(self.base_tx.price));
+
+        return create_typed_data(
+            super::tx::TransactionType::DeleteSpace,
+            tx_fields,
+            message,
+            mini_kvvm_domain(self.base_tx.magic, &self.base_tx.chain_id),
+        );
+    }
+}
+
+#[tokio::test]
+async fn delete_space_tx_test() {
+    use super::unsigned::Transaction;
+
+    let owner = ethereum_types::Address::zero();
+    let other = ethereum_types::Address::from_low_u64_be(1);
+    let db = avalanche_types::subnet::rpc::database::memdb::Database::new();
+    let ctx = |sender: ethereum_types::Address, block_time: u64| {
+        unsigned::TransactionContext::for_test(db.clone(), sender, block_time, block_time as u8)
+    };
+    let claim = crate::chain::tx::claim::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+    };
+    let tx = Tx {
+        base_tx: base::Tx {
+            price: 2,
+            ..Default::default()
+        },
+        space: "kvs".to_string(),
+    };
+
+    let resp = tx.execute(ctx(owner, 1)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::NotFound);
+
+    assert!(claim.execute(ctx(owner, 1)).await.is_ok());
+    let set = crate::chain::tx::set::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        key: "foo".to_string(),
+        value: "bar".as_bytes().to_vec(),
+        expiry: 0,
+        content_type: String::new(),
+        attributes: Default::default(),
+    };
+    assert!(set.execute(ctx(owner, 2)).await.is_ok());
+    let info = get_space_info(&db, "kvs".as_bytes())
+        .await
+        .unwrap()
+        .unwrap();
+
+    // only the owner may release the space
+    let resp = tx.execute(ctx(other, 3)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    // every key removed is charged
+    storage::add_balance(&mut db.clone(), &owner, 5)
+        .await
+        .unwrap();
+    assert!(tx.execute(ctx(owner, 3)).await.is_ok());
+    assert_eq!(storage::get_balance(&db, &owner).await.unwrap(), 3);
+    assert!(get_space_info(&db, "kvs".as_bytes())
+        .await
+        .unwrap()
+        .is_none());
+    assert!(!db
+        .has(&storage::space_value_key(info.raw_space, "foo".as_bytes()))
+        .await
+        .unwrap());
+    assert!(!db
+        .has(&storage::prefix_expiry_key(info.expiry, info.raw_space))
+        .await
+        .unwrap());
+
+    // the name can be claimed again right away
+    assert!(claim.execute(ctx(other, 4)).await.is_ok());
+    assert!(
+        storage::get_value(&db, "kvs".as_bytes(), "foo".as_bytes(), 0)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
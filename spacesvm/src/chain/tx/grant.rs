@@ -0,0 +1,337 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chain::{
+        codec::{Packer, Unpacker},
+        storage::{self, get_space_info, Grant},
+        tx::decoder::{create_typed_data, mini_kvvm_domain, MessageValue, Type, TypedData},
+        validation,
+    },
+    genesis::Genesis,
+};
+
+use super::{
+    base,
+    decoder::{
+        TD_ADDRESS, TD_BLOCK_ID, TD_BYTES32, TD_EXPIRY, TD_PREFIX, TD_PRICE, TD_SPACE, TD_STRING,
+        TD_TO, TD_UINT64,
+    },
+    tx::TransactionType,
+    unsigned,
+};
+
+/// Allows another address to set and delete keys of a space. A grant
+/// replaces any previous grant of the same address and is removed with the
+/// space once it expires.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Tx {
+    pub base_tx: base::Tx,
+
+    /// Space to grant write access to.
+    pub space: String,
+
+    /// Address which may write to the space.
+    pub to: ethereum_types::Address,
+
+    /// Only keys starting with the prefix may be written. Empty for all keys.
+    pub prefix: String,
+
+    /// Unix timestamp after which the grant no longer applies. Zero if the
+    /// grant does not expire.
+    pub expiry: u64,
+}
+
+impl Tx {
+    pub fn unpack(base_tx: base::Tx, unpacker: &mut Unpacker) -> Result<Self> {
+        Ok(Self {
+            base_tx,
+            space: unpacker.unpack_str()?,
+            to: unpacker.unpack_address()?,
+            prefix: unpacker.unpack_str()?,
+            expiry: unpacker.unpack_u64()?,
+        })
+    }
+}
+
+// important to define an unique name of the trait implementation
+#[typetag::serde(name = "grant")]
+#[tonic::async_trait]
+impl unsigned::Transaction for Tx {
+    async fn get_block_id(&self) -> avalanche_types::ids::Id {
+        self.base_tx.block_id
+    }
+
+    async fn set_block_id(&mut self, id: avalanche_types::ids::Id) {
+        self.base_tx.block_id = id;
+    }
+
+    async fn get_base_tx(&self) -> base::Tx {
+        self.base_tx
+    }
+
+    async fn set_base_tx(&mut self, base_tx: base::Tx) {
+        self.base_tx = base_tx;
+    }
+
+    async fn get_value(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    async fn set_value(&mut self, _value: Vec<u8>) -> std::io::Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "value is not supported for grant tx",
+        ))
+    }
+
+    async fn typ(&self) -> TransactionType {
+        TransactionType::Grant
+    }
+
+    async fn fee_units(&self, _genesis: &Genesis) -> u64 {
+        0
+    }
+
+    async fn validate(&self, _genesis: &Genesis) -> Result<()> {
+        validation::check_space(&self.space)?;
+        if !self.prefix.is_empty() {
+            validation::check_key(&self.prefix)?;
+        }
+        Ok(())
+    }
+
+    async fn pack(&self, packer: &mut Packer) {
+        packer.pack_str(&self.space);
+        packer.pack_address(&self.to);
+        packer.pack_str(&self.prefix);
+        packer.pack_u64(self.expiry);
+    }
+
+    async fn execute(&self, txn_ctx: unsigned::TransactionContext) -> Result<()> {
+        let mut db = txn_ctx.db;
+
+        if self.to.is_zero() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "grantee must not be the zero address",
+            ));
+        }
+        if self.expiry != 0 && self.expiry < txn_ctx.block_time {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("grant already expired: {}", self.expiry),
+            ));
+        }
+
+        let info = get_space_info(&db, self.space.as_bytes())
+            .await
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+        if info.is_none() {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("space not found: {}", self.space),
+            ));
+        }
+        let info = info.unwrap();
+        if info.is_expired(txn_ctx.block_time) {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("space expired: {}", self.space),
+            ));
+        }
+        if !info.is_owned_by(&txn_ctx.sender, &txn_ctx.cosigners) {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("grants only allowed for space owner: {}", self.space),
+            ));
+        }
+        if info.multisig.is_none() && info.owner == self.to {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("space is already owned by {:?}", self.to),
+            ));
+        }
+
+        log::debug!(
+            "execute: grant space: {} to: {:?} prefix: {} expiry: {}",
+            self.space,
+            self.to,
+            self.prefix,
+            self.expiry
+        );
+        let grant = Grant {
+            prefix: self.prefix.clone(),
+            expiry: self.expiry,
+            owner_epoch: info.owner_epoch,
+        };
+        storage::put_grant(&mut db, info.raw_space, &self.to, &grant)
+            .await
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))
+    }
+
+    async fn typed_data(&self) -> TypedData {
+        let mut tx_fields: Vec<Type> = Vec::new();
+        tx_fields.push(Type {
+            name: TD_SPACE.to_owned(),
+            type_: TD_STRING.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_TO.to_owned(),
+            type_: TD_ADDRESS.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_PREFIX.to_owned(),
+            type_: TD_STRING.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_EXPIRY.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_BLOCK_ID.to_owned(),
+            type_: TD_BYTES32.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_PRICE.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+
+        let mut message: HashMap<String, MessageValue> = HashMap::with_capacity(6);
+        message.insert(
+            TD_SPACE.to_owned(),
+            MessageValue::String(self.space.clone()),
+        );
+        message.insert(
+            TD_TO.to_owned(),
+            MessageValue::Bytes(self.to.as_bytes().to_vec()),
+        );
+        message.insert(
+            TD_PREFIX.to_owned(),
+            MessageValue::String(self.prefix.clone()),
+        );
+        message.insert(TD_EXPIRY.to_owned(), MessageValue::Uint(self.expiry));
+        message.insert(
+            TD_BLOCK_ID.to_owned(),
+            MessageValue::Bytes(self.base_tx.block_id.to_vec()),
+        );
+        message.insert(TD_PRICE.to_owned(), MessageValue::Uint(self.base_tx.price));
+
+        return create_typed_data(
+            super::tx::TransactionType::Grant,
+            tx_fields,
+            message,
+            mini_kvvm_domain(self.base_tx.magic, &self.base_tx.chain_id),
+        );
+    }
+}
+
+#[tokio::test]
+async fn grant_tx_test() {
+    use super::unsigned::Transaction;
+
+    let owner = ethereum_types::Address::zero();
+    let bot = ethereum_types::Address::from_low_u64_be(1);
+    let db = avalanche_types::subnet::rpc::database::memdb::Database::new();
+    let ctx = |sender: ethereum_types::Address, block_time: u64| {
+        unsigned::TransactionContext::for_test(db.clone(), sender, block_time, block_time as u8)
+    };
+    let set = |key: &str| crate::chain::tx::set::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        key: key.to_string(),
+        value: "bar".as_bytes().to_vec(),
+        expiry: 0,
+        content_type: String::new(),
+        attributes: Default::default(),
+    };
+
+    let tx = crate::chain::tx::claim::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+    };
+    assert!(tx.execute(ctx(owner, 0)).await.is_ok());
+
+    // without a grant only the owner may write
+    let resp = set("artifacts-1").execute(ctx(bot, 1)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    // only the owner may grant
+    let grant = Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        to: bot,
+        prefix: "artifacts-".to_string(),
+        expiry: 10,
+    };
+    let resp = grant.execute(ctx(bot, 1)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    assert!(grant.execute(ctx(owner, 1)).await.is_ok());
+
+    // grants are scoped to their prefix and expiry
+    assert!(set("artifacts-1").execute(ctx(bot, 2)).await.is_ok());
+    let resp = set("index").execute(ctx(bot, 2)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    let resp = set("artifacts-2").execute(ctx(bot, 11)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    let tx = crate::chain::tx::delete::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        key: "artifacts-1".to_string(),
+    };
+    assert!(tx.execute(ctx(bot, 3)).await.is_ok());
+
+    // revoked grants no longer apply
+    let revoke = super::revoke::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        to: bot,
+    };
+    assert!(revoke.execute(ctx(owner, 4)).await.is_ok());
+    let resp = set("artifacts-3").execute(ctx(bot, 5)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    let resp = revoke.execute(ctx(owner, 5)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::NotFound);
+
+    // grants of a previous owner no longer apply once the space moved
+    let grant = Tx { expiry: 0, ..grant };
+    assert!(grant.execute(ctx(owner, 6)).await.is_ok());
+    assert!(set("artifacts-4").execute(ctx(bot, 6)).await.is_ok());
+    let new_owner = ethereum_types::Address::from_low_u64_be(2);
+    let tx = super::move_space::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        to: new_owner,
+    };
+    assert!(tx.execute(ctx(owner, 7)).await.is_ok());
+    let resp = set("artifacts-5").execute(ctx(bot, 8)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    // until the new owner grants again
+    assert!(grant.execute(ctx(new_owner, 8)).await.is_ok());
+    assert!(set("artifacts-5").execute(ctx(bot, 9)).await.is_ok());
+
+    // the owner a multisig replaced is granted like any other address
+    let alice = ethereum_types::Address::from_low_u64_be(3);
+    let carol = ethereum_types::Address::from_low_u64_be(4);
+    let tx = super::multisig::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        threshold: 1,
+        members: vec![alice, carol],
+    };
+    assert!(tx.execute(ctx(new_owner, 10)).await.is_ok());
+    let resp = set("artifacts-6").execute(ctx(new_owner, 11)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    let grant = Tx {
+        to: new_owner,
+        ..grant
+    };
+    assert!(grant.execute(ctx(alice, 11)).await.is_ok());
+    assert!(set("artifacts-6").execute(ctx(new_owner, 12)).await.is_ok());
+}
@@ -0,0 +1,272 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chain::{
+        codec::{Packer, Unpacker},
+        tx::decoder::{create_typed_data, mini_kvvm_domain, MessageValue, Type, TypedData},
+        validation,
+    },
+    genesis::Genesis,
+};
+
+use super::{
+    append::update_value,
+    base,
+    decoder::{
+        TD_AMOUNT, TD_BLOCK_ID, TD_BYTES32, TD_KEY, TD_PRICE, TD_SPACE, TD_STRING, TD_UINT64,
+    },
+    tx::TransactionType,
+    unsigned,
+};
+
+/// Length of a counter value, a big endian u64.
+pub const COUNTER_LEN: usize = 8;
+
+/// Adds to the value of a key treated as a counter. Counters are stored as
+/// big endian u64 and start at zero if the key is missing.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Tx {
+    pub base_tx: base::Tx,
+
+    /// Space of the key.
+    pub space: String,
+
+    /// Key of the counter.
+    pub key: String,
+
+    /// Added to the counter, which must not overflow.
+    pub amount: u64,
+}
+
+impl Tx {
+    pub fn unpack(base_tx: base::Tx, unpacker: &mut Unpacker) -> Result<Self> {
+        Ok(Self {
+            base_tx,
+            space: unpacker.unpack_str()?,
+            key: unpacker.unpack_str()?,
+            amount: unpacker.unpack_u64()?,
+        })
+    }
+}
+
+/// Returns the counter stored in [value].
+pub fn parse_counter(value: &[u8]) -> Result<u64> {
+    let bytes: [u8; COUNTER_LEN] = value.try_into().map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "value is not a counter: {} bytes (expected: {})",
+                value.len(),
+                COUNTER_LEN
+            ),
+        )
+    })?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+// important to define an unique name of the trait implementation
+#[typetag::serde(name = "increment")]
+#[tonic::async_trait]
+impl unsigned::Transaction for Tx {
+    async fn get_block_id(&self) -> avalanche_types::ids::Id {
+        self.base_tx.block_id
+    }
+
+    async fn set_block_id(&mut self, id: avalanche_types::ids::Id) {
+        self.base_tx.block_id = id;
+    }
+
+    async fn get_base_tx(&self) -> base::Tx {
+        self.base_tx
+    }
+
+    async fn set_base_tx(&mut self, base_tx: base::Tx) {
+        self.base_tx = base_tx;
+    }
+
+    async fn get_value(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    async fn set_value(&mut self, _value: Vec<u8>) -> std::io::Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "value is not supported for increment tx",
+        ))
+    }
+
+    async fn typ(&self) -> TransactionType {
+        TransactionType::Increment
+    }
+
+    async fn fee_units(&self, _genesis: &Genesis) -> u64 {
+        0
+    }
+
+    async fn validate(&self, _genesis: &Genesis) -> Result<()> {
+        validation::check_space(&self.space)?;
+        validation::check_key(&self.key)
+    }
+
+    async fn pack(&self, packer: &mut Packer) {
+        packer.pack_str(&self.space);
+        packer.pack_str(&self.key);
+        packer.pack_u64(self.amount);
+    }
+
+    async fn execute(&self, txn_ctx: unsigned::TransactionContext) -> Result<()> {
+        log::debug!(
+            "execute: increment space: {} key: {} amount: {}",
+            self.space,
+            self.key,
+            self.amount
+        );
+        update_value(&txn_ctx, &self.space, &self.key, "increments", |current| {
+            let counter = match current {
+                None => 0,
+                Some(value) => parse_counter(&value)?,
+            };
+            let counter = counter.checked_add(self.amount).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("counter overflow: {} + {}", counter, self.amount),
+                )
+            })?;
+            Ok(counter.to_be_bytes().to_vec())
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn typed_data(&self) -> TypedData {
+        let mut tx_fields: Vec<Type> = Vec::new();
+        tx_fields.push(Type {
+            name: TD_SPACE.to_owned(),
+            type_: TD_STRING.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_KEY.to_owned(),
+            type_: TD_STRING.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_AMOUNT.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_BLOCK_ID.to_owned(),
+            type_: TD_BYTES32.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_PRICE.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+
+        let mut message: HashMap<String, MessageValue> = HashMap::with_capacity(5);
+        message.insert(
+            TD_SPACE.to_owned(),
+            MessageValue::String(self.space.clone()),
+        );
+        message.insert(TD_KEY.to_owned(), MessageValue::String(self.key.clone()));
+        message.insert(TD_AMOUNT.to_owned(), MessageValue::Uint(self.amount));
+        message.insert(
+            TD_BLOCK_ID.to_owned(),
+            MessageValue::Bytes(self.base_tx.block_id.to_vec()),
+        );
+        message.insert(TD_PRICE.to_owned(), MessageValue::Uint(self.base_tx.price));
+
+        return create_typed_data(
+            super::tx::TransactionType::Increment,
+            tx_fields,
+            message,
+            mini_kvvm_domain(self.base_tx.magic, &self.base_tx.chain_id),
+        );
+    }
+}
+
+#[tokio::test]
+async fn increment_tx_test() {
+    use super::unsigned::Transaction;
+    use crate::chain::storage;
+
+    let owner = ethereum_types::Address::zero();
+    let db = avalanche_types::subnet::rpc::database::memdb::Database::new();
+    let ctx = |block_time: u64| {
+        unsigned::TransactionContext::for_test(db.clone(), owner, block_time, block_time as u8)
+    };
+    let increment = |key: &str, amount: u64| Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        key: key.to_string(),
+        amount,
+    };
+    let counter = |block_time: u64| {
+        let db = &db;
+        async move {
+            let value = storage::get_value(db, "kvs".as_bytes(), "hits".as_bytes(), block_time)
+                .await
+                .unwrap()
+                .unwrap();
+            parse_counter(&value).unwrap()
+        }
+    };
+
+    let tx = crate::chain::tx::claim::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+    };
+    assert!(tx.execute(ctx(1)).await.is_ok());
+
+    // counters start at zero
+    assert!(increment("hits", 5).execute(ctx(2)).await.is_ok());
+    assert_eq!(counter(2).await, 5);
+    assert!(increment("hits", 2).execute(ctx(3)).await.is_ok());
+    assert_eq!(counter(3).await, 7);
+    let vmeta = storage::get_value_meta(&db, "kvs".as_bytes(), "hits".as_bytes(), 3)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(vmeta.size, COUNTER_LEN as u64);
+    assert_eq!(vmeta.created, 2);
+    assert_eq!(vmeta.updated, 3);
+
+    let resp = increment("hits", u64::MAX).execute(ctx(4)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert_eq!(counter(4).await, 7);
+
+    // only values of the counter length can be incremented
+    let set = crate::chain::tx::set::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        key: "name".to_string(),
+        value: "bar".as_bytes().to_vec(),
+        expiry: 0,
+        content_type: String::new(),
+        attributes: Default::default(),
+    };
+    assert!(set.execute(ctx(5)).await.is_ok());
+    let resp = increment("name", 1).execute(ctx(6)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::InvalidData);
+
+    // content addressed keys can not be incremented
+    let value = 1u64.to_be_bytes().to_vec();
+    let key = crate::chain::tx::set::value_hash(&value);
+    let set = crate::chain::tx::set::Tx {
+        key: key.clone(),
+        value: value.clone(),
+        ..set
+    };
+    assert!(set.execute(ctx(7)).await.is_ok());
+    let resp = increment(&key, 1).execute(ctx(8)).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert_eq!(
+        storage::get_value(&db, "kvs".as_bytes(), key.as_bytes(), 8)
+            .await
+            .unwrap(),
+        Some(value)
+    );
+}
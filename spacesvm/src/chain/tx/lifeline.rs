@@ -0,0 +1,303 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chain::{
+        codec::{Packer, Unpacker},
+        storage::{get_space_info, put_space_info},
+        tx::decoder::{create_typed_data, mini_kvvm_domain, MessageValue, Type, TypedData},
+        validation,
+    },
+    genesis::Genesis,
+};
+
+use super::{
+    base,
+    decoder::{TD_BLOCK_ID, TD_BYTES32, TD_PRICE, TD_SPACE, TD_STRING, TD_UINT64, TD_UNITS},
+    tx::TransactionType,
+    unsigned,
+};
+
+/// Extends the expiry of a space. Each unit adds another claim reward
+/// period to the current expiry.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Tx {
+    pub base_tx: base::Tx,
+
+    /// Space to renew.
+    pub space: String,
+
+    /// Number of claim reward periods to extend the space by.
+    pub units: u64,
+}
+
+impl Tx {
+    pub fn unpack(base_tx: base::Tx, unpacker: &mut Unpacker) -> Result<Self> {
+        Ok(Self {
+            base_tx,
+            space: unpacker.unpack_str()?,
+            units: unpacker.unpack_u64()?,
+        })
+    }
+}
+
+// important to define an unique name of the trait implementation
+#[typetag::serde(name = "lifeline")]
+#[tonic::async_trait]
+impl unsigned::Transaction for Tx {
+    async fn get_block_id(&self) -> avalanche_types::ids::Id {
+        self.base_tx.block_id
+    }
+
+    async fn set_block_id(&mut self, id: avalanche_types::ids::Id) {
+        self.base_tx.block_id = id;
+    }
+
+    async fn get_base_tx(&self) -> base::Tx {
+        self.base_tx
+    }
+
+    async fn set_base_tx(&mut self, base_tx: base::Tx) {
+        self.base_tx = base_tx;
+    }
+
+    async fn get_value(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    async fn set_value(&mut self, _value: Vec<u8>) -> std::io::Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "value is not supported for lifeline tx",
+        ))
+    }
+
+    async fn typ(&self) -> TransactionType {
+        TransactionType::Lifeline
+    }
+
+    async fn fee_units(&self, genesis: &Genesis) -> u64 {
+        self.units.saturating_mul(genesis.lifeline_units)
+    }
+
+    async fn validate(&self, _genesis: &Genesis) -> Result<()> {
+        validation::check_space(&self.space)
+    }
+
+    async fn pack(&self, packer: &mut Packer) {
+        packer.pack_str(&self.space);
+        packer.pack_u64(self.units);
+    }
+
+    async fn execute(&self, txn_ctx: unsigned::TransactionContext) -> Result<()> {
+        let mut db = txn_ctx.db;
+
+        if self.units == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "lifeline units must be greater than zero",
+            ));
+        }
+
+        let info = get_space_info(&db, self.space.as_bytes())
+            .await
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+        if info.is_none() {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("space not found: {}", self.space),
+            ));
+        }
+        let mut info = info.unwrap();
+        if info.is_expired(txn_ctx.block_time) {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("space expired: {}", self.space),
+            ));
+        }
+
+        let last_expiry = info.expiry;
+        info.expiry = txn_ctx
+            .genesis
+            .claim_reward
+            .checked_mul(self.units)
+            .and_then(|extension| last_expiry.checked_add(extension))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("expiry overflow: {}", self.space),
+                )
+            })?;
+        info.updated = txn_ctx.block_time;
+        log::debug!(
+            "execute: lifeline space: {} expiry: {} -> {}",
+            self.space,
+            last_expiry,
+            info.expiry
+        );
+
+        put_space_info(&mut db, self.space.as_bytes(), info, last_expiry)
+            .await
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))
+    }
+
+    async fn typed_data(&self) -> TypedData {
+        let mut tx_fields: Vec<Type> = Vec::new();
+        tx_fields.push(Type {
+            name: TD_SPACE.to_owned(),
+            type_: TD_STRING.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_UNITS.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_BLOCK_ID.to_owned(),
+            type_: TD_BYTES32.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_PRICE.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+
+        let mut message: HashMap<String, MessageValue> = HashMap::with_capacity(4);
+        message.insert(
+            TD_SPACE.to_owned(),
+            MessageValue::String(self.space.clone()),
+        );
+        message.insert(TD_UNITS.to_owned(), MessageValue::Uint(self.units));
+        message.insert(
+            TD_BLOCK_ID.to_owned(),
+            MessageValue::Bytes(self.base_tx.block_id.to_vec()),
+        );
+        message.insert(TD_PRICE.to_owned(), MessageValue::Uint(self.base_tx.price));
+
+        return create_typed_data(
+            super::tx::TransactionType::Lifeline,
+            tx_fields,
+            message,
+            mini_kvvm_domain(self.base_tx.magic, &self.base_tx.chain_id),
+        );
+    }
+}
+
+#[tokio::test]
+async fn lifeline_tx_test() {
+    use super::unsigned::Transaction;
+    use crate::chain::storage;
+    use std::str::FromStr;
+
+    let genesis = Genesis {
+        claim_reward: 100,
+        ..Default::default()
+    };
+    let owner = ethereum_types::Address::zero();
+    let other =
+        ethereum_types::Address::from_str("0000000000000000000000000000000000000001").unwrap();
+
+    // claim space at t=0 which expires at t=100
+    let db = avalanche_types::subnet::rpc::database::memdb::Database::new();
+    let ut_ctx = unsigned::TransactionContext {
+        db: db.clone(),
+        block_time: 0,
+        tx_id: avalanche_types::ids::Id::empty(),
+        sender: owner,
+        cosigners: vec![],
+        genesis: genesis.clone(),
+        chain_id: avalanche_types::ids::Id::empty(),
+    };
+    let tx = crate::chain::tx::claim::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+    };
+    assert!(tx.execute(ut_ctx).await.is_ok());
+    let info = storage::get_space_info(&db, "kvs".as_bytes())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(info.expiry, 100);
+
+    // extend by two periods
+    let ut_ctx = unsigned::TransactionContext {
+        db: db.clone(),
+        block_time: 50,
+        tx_id: avalanche_types::ids::Id::empty(),
+        sender: owner,
+        cosigners: vec![],
+        genesis: genesis.clone(),
+        chain_id: avalanche_types::ids::Id::empty(),
+    };
+    let tx = Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        units: 2,
+    };
+    assert!(tx.execute(ut_ctx).await.is_ok());
+    let info = storage::get_space_info(&db, "kvs".as_bytes())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(info.expiry, 300);
+
+    // space can not be claimed while it is alive
+    let ut_ctx = unsigned::TransactionContext {
+        db: db.clone(),
+        block_time: 300,
+        tx_id: avalanche_types::ids::Id::empty(),
+        sender: other,
+        cosigners: vec![],
+        genesis: genesis.clone(),
+        chain_id: avalanche_types::ids::Id::empty(),
+    };
+    let tx = crate::chain::tx::claim::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+    };
+    let resp = tx.execute(ut_ctx).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::AlreadyExists);
+
+    // expired space can not be renewed
+    let ut_ctx = unsigned::TransactionContext {
+        db: db.clone(),
+        block_time: 301,
+        tx_id: avalanche_types::ids::Id::empty(),
+        sender: owner,
+        cosigners: vec![],
+        genesis: genesis.clone(),
+        chain_id: avalanche_types::ids::Id::empty(),
+    };
+    let tx = Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        units: 1,
+    };
+    let resp = tx.execute(ut_ctx).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::NotFound);
+
+    // but it can be claimed by someone else
+    let ut_ctx = unsigned::TransactionContext {
+        db: db.clone(),
+        block_time: 301,
+        tx_id: avalanche_types::ids::Id::empty(),
+        sender: other,
+        cosigners: vec![],
+        genesis: genesis.clone(),
+        chain_id: avalanche_types::ids::Id::empty(),
+    };
+    let tx = crate::chain::tx::claim::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+    };
+    assert!(tx.execute(ut_ctx).await.is_ok());
+    let reclaimed = storage::get_space_info(&db, "kvs".as_bytes())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reclaimed.owner, other);
+    assert_eq!(reclaimed.expiry, 401);
+    assert_ne!(reclaimed.raw_space, info.raw_space);
+}
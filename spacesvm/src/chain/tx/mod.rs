@@ -1,8 +1,19 @@
+pub mod append;
 pub mod base;
+pub mod batch;
+pub mod cas;
 pub mod claim;
 pub mod decoder;
 pub mod delete;
+pub mod delete_space;
+pub mod grant;
+pub mod increment;
+pub mod lifeline;
+pub mod move_space;
+pub mod multisig;
+pub mod revoke;
 pub mod set;
+pub mod transfer;
 pub mod tx;
 pub mod unsigned;
 
@@ -10,7 +21,7 @@ use std::io::Result;
 
 use avalanche_types::{ids, subnet};
 
-use crate::block::Block;
+use crate::{block::Block, chain::vm::Context};
 
 #[tonic::async_trait]
 #[typetag::serde(tag = "type")]
@@ -23,5 +34,6 @@ pub trait Transaction {
         &self,
         db: &'life1 Box<dyn subnet::rpc::database::Database + Send + Sync>,
         block: &Block,
+        context: &Context,
     ) -> Result<()>;
 }
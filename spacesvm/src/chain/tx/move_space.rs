@@ -0,0 +1,291 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chain::{
+        codec::{Packer, Unpacker},
+        storage::{get_space_info, put_space_info},
+        tx::decoder::{create_typed_data, mini_kvvm_domain, MessageValue, Type, TypedData},
+        validation,
+    },
+    genesis::Genesis,
+};
+
+use super::{
+    base,
+    decoder::{
+        TD_ADDRESS, TD_BLOCK_ID, TD_BYTES32, TD_PRICE, TD_SPACE, TD_STRING, TD_TO, TD_UINT64,
+    },
+    tx::TransactionType,
+    unsigned,
+};
+
+/// Transfers ownership of a space to another address. The raw space and
+/// all of the keys stored in it are left untouched.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Tx {
+    pub base_tx: base::Tx,
+
+    /// Space to hand over.
+    pub space: String,
+
+    /// New owner of the space.
+    pub to: ethereum_types::Address,
+}
+
+impl Tx {
+    pub fn unpack(base_tx: base::Tx, unpacker: &mut Unpacker) -> Result<Self> {
+        Ok(Self {
+            base_tx,
+            space: unpacker.unpack_str()?,
+            to: unpacker.unpack_address()?,
+        })
+    }
+}
+
+// important to define an unique name of the trait implementation
+#[typetag::serde(name = "move")]
+#[tonic::async_trait]
+impl unsigned::Transaction for Tx {
+    async fn get_block_id(&self) -> avalanche_types::ids::Id {
+        self.base_tx.block_id
+    }
+
+    async fn set_block_id(&mut self, id: avalanche_types::ids::Id) {
+        self.base_tx.block_id = id;
+    }
+
+    async fn get_base_tx(&self) -> base::Tx {
+        self.base_tx
+    }
+
+    async fn set_base_tx(&mut self, base_tx: base::Tx) {
+        self.base_tx = base_tx;
+    }
+
+    async fn get_value(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    async fn set_value(&mut self, _value: Vec<u8>) -> std::io::Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "value is not supported for move tx",
+        ))
+    }
+
+    async fn typ(&self) -> TransactionType {
+        TransactionType::Move
+    }
+
+    async fn fee_units(&self, _genesis: &Genesis) -> u64 {
+        0
+    }
+
+    async fn validate(&self, _genesis: &Genesis) -> Result<()> {
+        validation::check_space(&self.space)
+    }
+
+    async fn pack(&self, packer: &mut Packer) {
+        packer.pack_str(&self.space);
+        packer.pack_address(&self.to);
+    }
+
+    async fn execute(&self, txn_ctx: unsigned::TransactionContext) -> Result<()> {
+        let mut db = txn_ctx.db;
+
+        if self.to.is_zero() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "new owner must not be the zero address",
+            ));
+        }
+
+        let info = get_space_info(&db, self.space.as_bytes())
+            .await
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+        if info.is_none() {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("space not found: {}", self.space),
+            ));
+        }
+        let mut info = info.unwrap();
+        if info.is_expired(txn_ctx.block_time) {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("space expired: {}", self.space),
+            ));
+        }
+        if !info.is_owned_by(&txn_ctx.sender, &txn_ctx.cosigners) {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("moves only allowed for space owner: {}", self.space),
+            ));
+        }
+        if info.multisig.is_none() && info.owner == self.to {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("space is already owned by {:?}", self.to),
+            ));
+        }
+
+        log::debug!(
+            "execute: move space: {} from: {:?} to: {:?}",
+            self.space,
+            info.owner,
+            self.to
+        );
+        // the new owner is a single address again
+        info.owner = self.to;
+        info.multisig = None;
+        info.owner_epoch += 1;
+        info.updated = txn_ctx.block_time;
+
+        put_space_info(&mut db, self.space.as_bytes(), info, 0)
+            .await
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))
+    }
+
+    async fn typed_data(&self) -> TypedData {
+        let mut tx_fields: Vec<Type> = Vec::new();
+        tx_fields.push(Type {
+            name: TD_SPACE.to_owned(),
+            type_: TD_STRING.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_TO.to_owned(),
+            type_: TD_ADDRESS.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_BLOCK_ID.to_owned(),
+            type_: TD_BYTES32.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_PRICE.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+
+        let mut message: HashMap<String, MessageValue> = HashMap::with_capacity(4);
+        message.insert(
+            TD_SPACE.to_owned(),
+            MessageValue::String(self.space.clone()),
+        );
+        message.insert(
+            TD_TO.to_owned(),
+            MessageValue::Bytes(self.to.as_bytes().to_vec()),
+        );
+        message.insert(
+            TD_BLOCK_ID.to_owned(),
+            MessageValue::Bytes(self.base_tx.block_id.to_vec()),
+        );
+        message.insert(TD_PRICE.to_owned(), MessageValue::Uint(self.base_tx.price));
+
+        return create_typed_data(
+            super::tx::TransactionType::Move,
+            tx_fields,
+            message,
+            mini_kvvm_domain(self.base_tx.magic, &self.base_tx.chain_id),
+        );
+    }
+}
+
+#[tokio::test]
+async fn move_tx_test() {
+    use super::unsigned::Transaction;
+    use crate::chain::storage;
+    use std::str::FromStr;
+
+    let owner = ethereum_types::Address::zero();
+    let new_owner =
+        ethereum_types::Address::from_str("0000000000000000000000000000000000000001").unwrap();
+
+    // create space and a key
+    let db = avalanche_types::subnet::rpc::database::memdb::Database::new();
+    let ut_ctx = unsigned::TransactionContext {
+        db: db.clone(),
+        block_time: 0,
+        tx_id: avalanche_types::ids::Id::empty(),
+        sender: owner,
+        cosigners: vec![],
+        genesis: crate::genesis::Genesis::default(),
+        chain_id: avalanche_types::ids::Id::empty(),
+    };
+    let tx = crate::chain::tx::claim::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+    };
+    assert!(tx.execute(ut_ctx).await.is_ok());
+
+    let ut_ctx = unsigned::TransactionContext {
+        db: db.clone(),
+        block_time: 0,
+        tx_id: avalanche_types::ids::Id::empty(),
+        sender: owner,
+        cosigners: vec![],
+        genesis: crate::genesis::Genesis::default(),
+        chain_id: avalanche_types::ids::Id::empty(),
+    };
+    let tx = crate::chain::tx::set::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        key: "foo".to_string(),
+        value: "bar".as_bytes().to_vec(),
+        expiry: 0,
+        content_type: String::new(),
+        attributes: Default::default(),
+    };
+    assert!(tx.execute(ut_ctx).await.is_ok());
+    let before = storage::get_space_info(&db, "kvs".as_bytes())
+        .await
+        .unwrap()
+        .unwrap();
+
+    // only the owner may move a space
+    let ut_ctx = unsigned::TransactionContext {
+        db: db.clone(),
+        block_time: 1,
+        tx_id: avalanche_types::ids::Id::empty(),
+        sender: new_owner,
+        cosigners: vec![],
+        genesis: crate::genesis::Genesis::default(),
+        chain_id: avalanche_types::ids::Id::empty(),
+    };
+    let tx = Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        to: new_owner,
+    };
+    let resp = tx.execute(ut_ctx).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    let ut_ctx = unsigned::TransactionContext {
+        db: db.clone(),
+        block_time: 1,
+        tx_id: avalanche_types::ids::Id::empty(),
+        sender: owner,
+        cosigners: vec![],
+        genesis: crate::genesis::Genesis::default(),
+        chain_id: avalanche_types::ids::Id::empty(),
+    };
+    assert!(tx.execute(ut_ctx).await.is_ok());
+
+    // ownership changed while the raw space and its keys were kept
+    let after = storage::get_space_info(&db, "kvs".as_bytes())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(after.owner, new_owner);
+    assert_eq!(after.raw_space, before.raw_space);
+    assert_eq!(after.updated, 1);
+    assert!(
+        storage::get_value_meta(&db, "kvs".as_bytes(), "foo".as_bytes(), 0)
+            .await
+            .unwrap()
+            .is_some()
+    );
+}
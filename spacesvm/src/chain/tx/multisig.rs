@@ -0,0 +1,340 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chain::{
+        codec::{Packer, Unpacker},
+        storage::{get_space_info, put_space_info},
+        tx::decoder::{create_typed_data, mini_kvvm_domain, MessageValue, Type, TypedData},
+        validation,
+    },
+    genesis::Genesis,
+};
+
+use super::{
+    base,
+    claim::Multisig,
+    decoder::{
+        TD_ADDRESS, TD_BLOCK_ID, TD_BYTES32, TD_MEMBERS, TD_PRICE, TD_SPACE, TD_STRING,
+        TD_THRESHOLD, TD_UINT64,
+    },
+    tx::TransactionType,
+    unsigned,
+};
+
+/// Maximum number of members of a multisig and of cosigners of a tx.
+pub const MAX_MEMBERS: usize = 16;
+
+/// Makes a space owned by [threshold] of [members] instead of a single
+/// address. The tx must be authorized by the current owner of the space, so
+/// changing or removing a multisig requires its threshold. A zero threshold
+/// without members makes the sender the single owner again.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Tx {
+    pub base_tx: base::Tx,
+
+    /// Space to change the owner of.
+    pub space: String,
+
+    /// Number of members which must sign a tx acting as the owner.
+    pub threshold: u64,
+
+    /// Addresses which own the space together.
+    pub members: Vec<ethereum_types::Address>,
+}
+
+impl Tx {
+    pub fn unpack(base_tx: base::Tx, unpacker: &mut Unpacker) -> Result<Self> {
+        let space = unpacker.unpack_str()?;
+        let threshold = unpacker.unpack_u64()?;
+        let count = unpacker.unpack_u32()?;
+        let mut members = Vec::new();
+        for _ in 0..count {
+            members.push(unpacker.unpack_address()?);
+        }
+        Ok(Self {
+            base_tx,
+            space,
+            threshold,
+            members,
+        })
+    }
+}
+
+// important to define an unique name of the trait implementation
+#[typetag::serde(name = "multisig")]
+#[tonic::async_trait]
+impl unsigned::Transaction for Tx {
+    async fn get_block_id(&self) -> avalanche_types::ids::Id {
+        self.base_tx.block_id
+    }
+
+    async fn set_block_id(&mut self, id: avalanche_types::ids::Id) {
+        self.base_tx.block_id = id;
+    }
+
+    async fn get_base_tx(&self) -> base::Tx {
+        self.base_tx
+    }
+
+    async fn set_base_tx(&mut self, base_tx: base::Tx) {
+        self.base_tx = base_tx;
+    }
+
+    async fn get_value(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    async fn set_value(&mut self, _value: Vec<u8>) -> std::io::Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "value is not supported for multisig tx",
+        ))
+    }
+
+    async fn typ(&self) -> TransactionType {
+        TransactionType::Multisig
+    }
+
+    async fn fee_units(&self, _genesis: &Genesis) -> u64 {
+        0
+    }
+
+    async fn validate(&self, _genesis: &Genesis) -> Result<()> {
+        validation::check_space(&self.space)?;
+        if self.threshold == 0 && self.members.is_empty() {
+            return Ok(());
+        }
+        if self.members.len() > MAX_MEMBERS {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "too many members: {} (max: {})",
+                    self.members.len(),
+                    MAX_MEMBERS
+                ),
+            ));
+        }
+        if self.threshold == 0 || self.threshold > self.members.len() as u64 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "invalid threshold: {} of {} members",
+                    self.threshold,
+                    self.members.len()
+                ),
+            ));
+        }
+        for (i, member) in self.members.iter().enumerate() {
+            if member.is_zero() {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "member must not be the zero address",
+                ));
+            }
+            if self.members[..i].contains(member) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("duplicate member: {:?}", member),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    async fn pack(&self, packer: &mut Packer) {
+        packer.pack_str(&self.space);
+        packer.pack_u64(self.threshold);
+        packer.pack_u32(self.members.len() as u32);
+        for member in self.members.iter() {
+            packer.pack_address(member);
+        }
+    }
+
+    async fn execute(&self, txn_ctx: unsigned::TransactionContext) -> Result<()> {
+        let mut db = txn_ctx.db;
+
+        let info = get_space_info(&db, self.space.as_bytes())
+            .await
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+        if info.is_none() {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("space not found: {}", self.space),
+            ));
+        }
+        let mut info = info.unwrap();
+        if info.is_expired(txn_ctx.block_time) {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("space expired: {}", self.space),
+            ));
+        }
+        if !info.is_owned_by(&txn_ctx.sender, &txn_ctx.cosigners) {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("multisig only allowed for space owner: {}", self.space),
+            ));
+        }
+
+        log::debug!(
+            "execute: multisig space: {} threshold: {} members: {:?}",
+            self.space,
+            self.threshold,
+            self.members
+        );
+        if self.members.is_empty() {
+            info.owner = txn_ctx.sender;
+            info.multisig = None;
+        } else {
+            info.multisig = Some(Multisig {
+                threshold: self.threshold,
+                members: self.members.clone(),
+            });
+        }
+        info.owner_epoch += 1;
+        info.updated = txn_ctx.block_time;
+
+        put_space_info(&mut db, self.space.as_bytes(), info, 0)
+            .await
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))
+    }
+
+    async fn typed_data(&self) -> TypedData {
+        let mut tx_fields: Vec<Type> = Vec::new();
+        tx_fields.push(Type {
+            name: TD_SPACE.to_owned(),
+            type_: TD_STRING.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_THRESHOLD.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_MEMBERS.to_owned(),
+            type_: format!("{}[]", TD_ADDRESS),
+        });
+        tx_fields.push(Type {
+            name: TD_BLOCK_ID.to_owned(),
+            type_: TD_BYTES32.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_PRICE.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+
+        let mut message: HashMap<String, MessageValue> = HashMap::with_capacity(5);
+        message.insert(
+            TD_SPACE.to_owned(),
+            MessageValue::String(self.space.clone()),
+        );
+        message.insert(TD_THRESHOLD.to_owned(), MessageValue::Uint(self.threshold));
+        message.insert(
+            TD_MEMBERS.to_owned(),
+            MessageValue::Array(
+                self.members
+                    .iter()
+                    .map(|m| MessageValue::Bytes(m.as_bytes().to_vec()))
+                    .collect(),
+            ),
+        );
+        message.insert(
+            TD_BLOCK_ID.to_owned(),
+            MessageValue::Bytes(self.base_tx.block_id.to_vec()),
+        );
+        message.insert(TD_PRICE.to_owned(), MessageValue::Uint(self.base_tx.price));
+
+        return create_typed_data(
+            super::tx::TransactionType::Multisig,
+            tx_fields,
+            message,
+            mini_kvvm_domain(self.base_tx.magic, &self.base_tx.chain_id),
+        );
+    }
+}
+
+#[tokio::test]
+async fn multisig_tx_test() {
+    use super::unsigned::Transaction;
+
+    let owner = ethereum_types::Address::from_low_u64_be(1);
+    let alice = ethereum_types::Address::from_low_u64_be(2);
+    let bob = ethereum_types::Address::from_low_u64_be(3);
+    let carol = ethereum_types::Address::from_low_u64_be(4);
+    let db = avalanche_types::subnet::rpc::database::memdb::Database::new();
+    let ctx = |sender: ethereum_types::Address, cosigners: Vec<ethereum_types::Address>| {
+        unsigned::TransactionContext {
+            cosigners,
+            ..unsigned::TransactionContext::for_test(db.clone(), sender, 1, 0)
+        }
+    };
+    let set = crate::chain::tx::set::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        key: "foo".to_string(),
+        value: "bar".as_bytes().to_vec(),
+        expiry: 0,
+        content_type: String::new(),
+        attributes: Default::default(),
+    };
+
+    let tx = crate::chain::tx::claim::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+    };
+    assert!(tx.execute(ctx(owner, vec![])).await.is_ok());
+
+    // thresholds must be reachable by distinct members
+    let mut tx = Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        threshold: 3,
+        members: vec![alice, bob],
+    };
+    assert!(tx.validate(&Genesis::default()).await.is_err());
+    tx.members = vec![alice, bob, bob];
+    assert!(tx.validate(&Genesis::default()).await.is_err());
+    tx.threshold = 2;
+    tx.members = vec![alice, bob, carol];
+    assert!(tx.validate(&Genesis::default()).await.is_ok());
+    assert!(tx.execute(ctx(owner, vec![])).await.is_ok());
+
+    // the previous owner and single members no longer own the space
+    let resp = set.execute(ctx(owner, vec![])).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    let resp = set.execute(ctx(alice, vec![])).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    let resp = set.execute(ctx(owner, vec![alice])).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    assert!(set.execute(ctx(alice, vec![carol])).await.is_ok());
+    assert!(set.execute(ctx(owner, vec![bob, carol])).await.is_ok());
+
+    // the threshold is needed to remove the multisig
+    let clear = Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        threshold: 0,
+        members: vec![],
+    };
+    let resp = clear.execute(ctx(bob, vec![])).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    assert!(clear.execute(ctx(bob, vec![alice])).await.is_ok());
+    let resp = set.execute(ctx(alice, vec![carol])).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    assert!(set.execute(ctx(bob, vec![])).await.is_ok());
+
+    // typed data round-trips
+    let typed_data = tx.typed_data().await;
+    let json = serde_json::to_string(&typed_data).unwrap();
+    let parsed: TypedData = serde_json::from_str(&json).unwrap();
+    let parsed = parsed.parse_typed_data().unwrap();
+    let mut expected = Packer::new();
+    tx.pack(&mut expected).await;
+    let mut actual = Packer::new();
+    parsed.pack(&mut actual).await;
+    assert_eq!(actual.take_bytes().unwrap(), expected.take_bytes().unwrap());
+}
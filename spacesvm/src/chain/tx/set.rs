@@ -1,25 +1,34 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     io::{Error, ErrorKind},
 };
 
 use serde::{Deserialize, Serialize};
-use sha3::Digest;
 
-use crate::chain::{
-    storage::{self, get_space_info, put_space_info, put_space_key, ValueMeta},
-    tx::decoder::{create_typed_data, MessageValue, Type, TypedData},
+use crate::{
+    chain::{
+        codec::{Packer, Unpacker},
+        storage::{
+            self, get_space_info, prefix_tx_value_key, put_space_info, put_space_key, ValueMeta,
+        },
+        tx::decoder::{create_typed_data, mini_kvvm_domain, MessageValue, Type, TypedData},
+        validation,
+    },
+    genesis::Genesis,
 };
 
 use super::{
     base,
-    decoder::{TD_BLOCK_ID, TD_BYTES, TD_KEY, TD_SPACE, TD_STRING, TD_VALUE},
+    decoder::{
+        TD_ATTRIBUTE, TD_ATTRIBUTES, TD_BLOCK_ID, TD_BYTES, TD_BYTES32, TD_CONTENT_TYPE, TD_EXPIRY,
+        TD_KEY, TD_NAME, TD_PRICE, TD_SPACE, TD_STRING, TD_UINT64, TD_VALUE,
+    },
     tx::TransactionType,
     unsigned::{self},
 };
 
 /// 0x + hex-encoded hash
-const HASH_LEN: usize = 66;
+pub(super) const HASH_LEN: usize = 66;
 
 /// Performs a write against the logical keyspace. If the key exists
 /// the value will be overwritten. The space must be created in
@@ -38,6 +47,66 @@ pub struct Tx {
     /// Written as the key-value pair to the storage. If a previous value
     /// exists, it is overwritten.
     pub value: Vec<u8>,
+
+    /// Unix timestamp after which the key is treated as missing and removed.
+    /// Zero if the key does not expire.
+    #[serde(default)]
+    pub expiry: u64,
+
+    /// MIME type of the value such as "application/json". Empty if unknown.
+    #[serde(default)]
+    pub content_type: String,
+
+    /// User defined name and value pairs stored with the value.
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl Tx {
+    pub fn unpack(base_tx: base::Tx, unpacker: &mut Unpacker) -> std::io::Result<Self> {
+        Ok(Self {
+            base_tx,
+            space: unpacker.unpack_str()?,
+            key: unpacker.unpack_str()?,
+            value: unpacker.unpack_bytes()?,
+            expiry: 0,
+            content_type: String::new(),
+            attributes: BTreeMap::new(),
+        })
+    }
+
+    /// Unpacks a set tx encoded with its expiry, content type and attributes
+    /// after the value. At least one of them must be set and the attributes
+    /// must be sorted by name, so every set tx has a single valid encoding.
+    pub fn unpack_extended(base_tx: base::Tx, unpacker: &mut Unpacker) -> std::io::Result<Self> {
+        let mut tx = Self::unpack(base_tx, unpacker)?;
+        tx.expiry = unpacker.unpack_u64()?;
+        tx.content_type = unpacker.unpack_str()?;
+        let count = unpacker.unpack_u32()?;
+        for _ in 0..count {
+            let name = unpacker.unpack_str()?;
+            let value = unpacker.unpack_str()?;
+            if tx.attributes.keys().next_back() >= Some(&name) {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("attribute out of order: {:?}", name),
+                ));
+            }
+            tx.attributes.insert(name, value);
+        }
+        if !tx.is_extended_set() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "extended set tx without expiry, content type or attributes",
+            ));
+        }
+        Ok(tx)
+    }
+
+    /// Returns true if the tx uses fields the Go SpacesVM does not know.
+    fn is_extended_set(&self) -> bool {
+        self.expiry != 0 || !self.content_type.is_empty() || !self.attributes.is_empty()
+    }
 }
 
 // important to define an unique name of the trait implementation
@@ -52,6 +121,14 @@ impl unsigned::Transaction for Tx {
         self.base_tx.block_id = id;
     }
 
+    async fn get_base_tx(&self) -> base::Tx {
+        self.base_tx
+    }
+
+    async fn set_base_tx(&mut self, base_tx: base::Tx) {
+        self.base_tx = base_tx;
+    }
+
     async fn get_value(&self) -> Option<Vec<u8>> {
         Some(self.value.clone())
     }
@@ -61,13 +138,46 @@ impl unsigned::Transaction for Tx {
         Ok(())
     }
 
+    async fn is_extended(&self) -> bool {
+        self.is_extended_set()
+    }
+
     async fn typ(&self) -> TransactionType {
         TransactionType::Set
     }
 
+    async fn fee_units(&self, _genesis: &Genesis) -> u64 {
+        0
+    }
+
+    async fn validate(&self, genesis: &Genesis) -> std::io::Result<()> {
+        validation::check_space(&self.space)?;
+        validation::check_key(&self.key)?;
+        validation::check_value(&self.value, genesis)?;
+        if !self.content_type.is_empty() {
+            validation::check_content_type(&self.content_type)?;
+        }
+        validation::check_attributes(&self.attributes)
+    }
+
+    async fn pack(&self, packer: &mut Packer) {
+        packer.pack_str(&self.space);
+        packer.pack_str(&self.key);
+        packer.pack_bytes(&self.value);
+        // other sets keep the layout of the Go SpacesVM
+        if self.is_extended_set() {
+            packer.pack_u64(self.expiry);
+            packer.pack_str(&self.content_type);
+            packer.pack_u32(self.attributes.len() as u32);
+            for (name, value) in self.attributes.iter() {
+                packer.pack_str(name);
+                packer.pack_str(value);
+            }
+        }
+    }
+
     async fn execute(&self, txn_ctx: unsigned::TransactionContext) -> std::io::Result<()> {
         let mut db = txn_ctx.db;
-        // TODO: ensure expected format of space, key and value
 
         if self.key.len() == HASH_LEN {
             let hash = value_hash(&self.value);
@@ -79,6 +189,13 @@ impl unsigned::Transaction for Tx {
             }
         }
 
+        if self.expiry != 0 && self.expiry < txn_ctx.block_time {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("value already expired: {}", self.expiry),
+            ));
+        }
+
         let value_size = self.value.len() as u64;
 
         let mut new_vmeta = ValueMeta {
@@ -86,9 +203,18 @@ impl unsigned::Transaction for Tx {
             tx_id: txn_ctx.tx_id,
             created: txn_ctx.block_time,
             updated: txn_ctx.block_time,
+            expiry: self.expiry,
+            content_type: self.content_type.clone(),
+            attributes: self.attributes.clone(),
         };
 
-        let v = storage::get_value_meta(&db, self.space.as_bytes(), self.key.as_bytes()).await?;
+        let v = storage::get_value_meta(
+            &db,
+            self.space.as_bytes(),
+            self.key.as_bytes(),
+            txn_ctx.block_time,
+        )
+        .await?;
         if v.is_none() {
             new_vmeta.created = txn_ctx.block_time;
         }
@@ -103,7 +229,23 @@ impl unsigned::Transaction for Tx {
             ));
         }
         let info = info.unwrap();
-        if info.owner != txn_ctx.sender {
+        if info.is_expired(txn_ctx.block_time) {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("space expired: {}", self.space),
+            ));
+        }
+        let allowed = storage::can_write(
+            &db,
+            &info,
+            &txn_ctx.sender,
+            &txn_ctx.cosigners,
+            self.key.as_bytes(),
+            txn_ctx.block_time,
+        )
+        .await
+        .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+        if !allowed {
             log::debug!(
                 "execute: owner: {}\n sender: {}",
                 &info.owner,
@@ -111,7 +253,10 @@ impl unsigned::Transaction for Tx {
             );
             return Err(Error::new(
                 ErrorKind::PermissionDenied,
-                format!("sets only allowed for spaced owner: {}", self.space),
+                format!(
+                    "sets only allowed for space owner or grantees: {}",
+                    self.space
+                ),
             ));
         }
 
@@ -131,15 +276,20 @@ impl unsigned::Transaction for Tx {
             self.space.as_bytes(),
             self.key.as_bytes(),
             new_vmeta,
+            &txn_ctx.genesis,
         )
         .await
         .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
 
-        Ok(())
+        // stored right away so later txs can read the value before the block
+        // is accepted
+        db.put(&prefix_tx_value_key(&txn_ctx.tx_id), &self.value)
+            .await
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))
     }
 
     async fn typed_data(&self) -> TypedData {
-        let mut tx_fields: Vec<Type> = vec![];
+        let mut tx_fields: Vec<Type> = Vec::new();
         tx_fields.push(Type {
             name: TD_SPACE.to_owned(),
             type_: TD_STRING.to_owned(),
@@ -152,38 +302,108 @@ impl unsigned::Transaction for Tx {
             name: TD_VALUE.to_owned(),
             type_: TD_BYTES.to_owned(),
         });
+        // only declared if set so the typed data of other sets keeps the
+        // fields of a plain set
+        if self.expiry != 0 {
+            tx_fields.push(Type {
+                name: TD_EXPIRY.to_owned(),
+                type_: TD_UINT64.to_owned(),
+            });
+        }
+        if !self.content_type.is_empty() {
+            tx_fields.push(Type {
+                name: TD_CONTENT_TYPE.to_owned(),
+                type_: TD_STRING.to_owned(),
+            });
+        }
+        if !self.attributes.is_empty() {
+            tx_fields.push(Type {
+                name: TD_ATTRIBUTES.to_owned(),
+                type_: format!("{}[]", TD_ATTRIBUTE),
+            });
+        }
         tx_fields.push(Type {
             name: TD_BLOCK_ID.to_owned(),
-            type_: TD_STRING.to_owned(),
+            type_: TD_BYTES32.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_PRICE.to_owned(),
+            type_: TD_UINT64.to_owned(),
         });
 
-        let mut message = HashMap::with_capacity(3);
+        let mut message: HashMap<String, MessageValue> = HashMap::with_capacity(5);
         message.insert(
             TD_SPACE.to_owned(),
-            MessageValue::Vec(self.space.as_bytes().to_vec()),
-        );
-        message.insert(
-            TD_KEY.to_owned(),
-            MessageValue::Vec(self.key.as_bytes().to_vec()),
+            MessageValue::String(self.space.clone()),
         );
+        message.insert(TD_KEY.to_owned(), MessageValue::String(self.key.clone()));
         message.insert(
             TD_VALUE.to_owned(),
             MessageValue::Bytes(self.value.to_vec()),
         );
+        if self.expiry != 0 {
+            message.insert(TD_EXPIRY.to_owned(), MessageValue::Uint(self.expiry));
+        }
+        if !self.content_type.is_empty() {
+            message.insert(
+                TD_CONTENT_TYPE.to_owned(),
+                MessageValue::String(self.content_type.clone()),
+            );
+        }
+        if !self.attributes.is_empty() {
+            message.insert(
+                TD_ATTRIBUTES.to_owned(),
+                MessageValue::Array(
+                    self.attributes
+                        .iter()
+                        .map(|(name, value)| {
+                            MessageValue::Struct(HashMap::from([
+                                (TD_NAME.to_owned(), MessageValue::String(name.clone())),
+                                (TD_VALUE.to_owned(), MessageValue::String(value.clone())),
+                            ]))
+                        })
+                        .collect(),
+                ),
+            );
+        }
         message.insert(
             TD_BLOCK_ID.to_owned(),
-            MessageValue::Vec(self.base_tx.block_id.to_vec()),
+            MessageValue::Bytes(self.base_tx.block_id.to_vec()),
         );
+        message.insert(TD_PRICE.to_owned(), MessageValue::Uint(self.base_tx.price));
 
-        return create_typed_data(super::tx::TransactionType::Set, tx_fields, message);
+        let mut typed_data = create_typed_data(
+            super::tx::TransactionType::Set,
+            tx_fields,
+            message,
+            mini_kvvm_domain(self.base_tx.magic, &self.base_tx.chain_id),
+        );
+        if !self.attributes.is_empty() {
+            typed_data.types.insert(
+                TD_ATTRIBUTE.to_owned(),
+                vec![
+                    Type {
+                        name: TD_NAME.to_owned(),
+                        type_: TD_STRING.to_owned(),
+                    },
+                    Type {
+                        name: TD_VALUE.to_owned(),
+                        type_: TD_STRING.to_owned(),
+                    },
+                ],
+            );
+        }
+        typed_data
     }
 }
 
-fn value_hash(value: &[u8]) -> String {
-    let mut hasher = sha3::Sha3_256::new();
-    hasher.update(value);
-    let result = hasher.finalize();
-    hex::encode(&result[..])
+/// Returns the key a value may be stored under by its content, the 0x
+/// prefixed hex keccak256 hash of the value as in the Go SpacesVM.
+pub fn value_hash(value: &[u8]) -> String {
+    format!(
+        "0x{}",
+        hex::encode(avalanche_types::hash::keccak256(value).as_bytes())
+    )
 }
 
 #[tokio::test]
@@ -198,12 +418,18 @@ async fn set_tx_test() {
         block_time: 0,
         tx_id: avalanche_types::ids::Id::empty(),
         sender: ethereum_types::Address::zero(),
+        cosigners: vec![],
+        genesis: crate::genesis::Genesis::default(),
+        chain_id: avalanche_types::ids::Id::empty(),
     };
     let tx = Tx {
         base_tx: base::Tx::default(),
         space: "kvs".to_string(),
         key: "foo".to_string(),
         value: "bar".as_bytes().to_vec(),
+        expiry: 0,
+        content_type: String::new(),
+        attributes: Default::default(),
     };
     let resp = tx.execute(ut_ctx).await;
     assert!(resp.unwrap_err().kind() == ErrorKind::NotFound);
@@ -215,6 +441,9 @@ async fn set_tx_test() {
         block_time: 0,
         tx_id: avalanche_types::ids::Id::empty(),
         sender: ethereum_types::Address::zero(),
+        cosigners: vec![],
+        genesis: crate::genesis::Genesis::default(),
+        chain_id: avalanche_types::ids::Id::empty(),
     };
     let tx = crate::chain::tx::claim::Tx {
         base_tx: base::Tx::default(),
@@ -231,12 +460,18 @@ async fn set_tx_test() {
         block_time: 0,
         tx_id: avalanche_types::ids::Id::empty(),
         sender: other_account,
+        cosigners: vec![],
+        genesis: crate::genesis::Genesis::default(),
+        chain_id: avalanche_types::ids::Id::empty(),
     };
     let tx = Tx {
         base_tx: base::Tx::default(),
         space: "kvs".to_string(),
         key: "foo".to_string(),
         value: "bar".as_bytes().to_vec(),
+        expiry: 0,
+        content_type: String::new(),
+        attributes: Default::default(),
     };
     let resp = tx.execute(ut_ctx).await;
     assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
@@ -247,12 +482,18 @@ async fn set_tx_test() {
         block_time: 0,
         tx_id: avalanche_types::ids::Id::empty(),
         sender: ethereum_types::Address::zero(),
+        cosigners: vec![],
+        genesis: crate::genesis::Genesis::default(),
+        chain_id: avalanche_types::ids::Id::empty(),
     };
     let tx = Tx {
         base_tx: base::Tx::default(),
         space: "kvs".to_string(),
         key: "foo".to_string(),
         value: "bar".as_bytes().to_vec(),
+        expiry: 0,
+        content_type: String::new(),
+        attributes: Default::default(),
     };
     let resp = tx.execute(ut_ctx).await;
     assert!(resp.is_ok());
@@ -262,13 +503,129 @@ async fn set_tx_test() {
         block_time: 0,
         tx_id: avalanche_types::ids::Id::empty(),
         sender: ethereum_types::Address::zero(),
+        cosigners: vec![],
+        genesis: crate::genesis::Genesis::default(),
+        chain_id: avalanche_types::ids::Id::empty(),
     };
     let tx = Tx {
         base_tx: base::Tx::default(),
         space: "kvs".to_string(),
         key: "bar".to_string(),
         value: "bar".as_bytes().to_vec(),
+        expiry: 0,
+        content_type: String::new(),
+        attributes: Default::default(),
     };
     let resp = tx.execute(ut_ctx).await;
     assert!(resp.is_ok());
 }
+
+#[tokio::test]
+async fn set_tx_metadata_test() {
+    use super::unsigned::Transaction;
+    use crate::chain::codec;
+    use avalanche_types::ids;
+
+    let db = avalanche_types::subnet::rpc::database::memdb::Database::new();
+    let ctx = || {
+        unsigned::TransactionContext::for_test(db.clone(), ethereum_types::Address::zero(), 1, 0)
+    };
+    let tx = crate::chain::tx::claim::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+    };
+    assert!(tx.execute(ctx()).await.is_ok());
+
+    let mut tx = Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        key: "foo".to_string(),
+        value: "{}".as_bytes().to_vec(),
+        expiry: 0,
+        content_type: "application/json".to_string(),
+        attributes: BTreeMap::from([
+            ("author".to_string(), "jim".to_string()),
+            ("app".to_string(), "notes".to_string()),
+        ]),
+    };
+    assert!(tx.validate(&Genesis::default()).await.is_ok());
+    assert!(tx.execute(ctx()).await.is_ok());
+
+    // the metadata is stored with the value and counts against the quota
+    let vmeta = storage::get_value_meta(&db, "kvs".as_bytes(), "foo".as_bytes(), 1)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(vmeta.content_type, "application/json");
+    assert_eq!(vmeta.attributes, tx.attributes);
+    let info = get_space_info(&db, "kvs".as_bytes())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(info.usage.bytes, 3 + 2 + 16 + 6 + 3 + 3 + 5);
+
+    // the encoding round-trips under the extended type id
+    let utx: Box<dyn Transaction + Send + Sync> = Box::new(tx.clone());
+    let mut packer = codec::Packer::new();
+    codec::pack_unsigned(&mut packer, &utx).await.unwrap();
+    let bytes = packer.take_bytes().unwrap();
+    assert_eq!(&bytes[..4], &13u32.to_be_bytes());
+    let parsed =
+        codec::unpack_unsigned(&mut codec::Unpacker::new(&bytes), ids::Id::empty()).unwrap();
+    let mut packer = codec::Packer::new();
+    codec::pack_unsigned(&mut packer, &parsed).await.unwrap();
+    assert_eq!(packer.take_bytes().unwrap(), bytes);
+
+    // and so does the typed data
+    let typed_data = tx.typed_data().await;
+    let json = serde_json::to_string(&typed_data).unwrap();
+    let parsed: TypedData = serde_json::from_str(&json).unwrap();
+    let parsed = parsed.parse_typed_data().unwrap();
+    let mut expected = Packer::new();
+    tx.pack(&mut expected).await;
+    let mut actual = Packer::new();
+    parsed.pack(&mut actual).await;
+    assert_eq!(actual.take_bytes().unwrap(), expected.take_bytes().unwrap());
+
+    // invalid metadata is rejected
+    tx.content_type = "json".to_string();
+    assert!(tx.validate(&Genesis::default()).await.is_err());
+    tx.content_type = String::new();
+    tx.attributes = BTreeMap::from([("Author".to_string(), "jim".to_string())]);
+    assert!(tx.validate(&Genesis::default()).await.is_err());
+}
+
+#[tokio::test]
+async fn set_tx_value_hash_test() {
+    use super::unsigned::Transaction;
+
+    let db = avalanche_types::subnet::rpc::database::memdb::Database::new();
+    let ctx = || {
+        unsigned::TransactionContext::for_test(db.clone(), ethereum_types::Address::zero(), 1, 0)
+    };
+    let tx = crate::chain::tx::claim::Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+    };
+    assert!(tx.execute(ctx()).await.is_ok());
+
+    // keys of the length of a hash must be the hash of the value
+    let value = "bar".as_bytes().to_vec();
+    let key = value_hash(&value);
+    assert_eq!(key.len(), HASH_LEN);
+    let mut tx = Tx {
+        base_tx: base::Tx::default(),
+        space: "kvs".to_string(),
+        key,
+        value,
+        expiry: 0,
+        content_type: String::new(),
+        attributes: Default::default(),
+    };
+    assert!(tx.validate(&Genesis::default()).await.is_ok());
+    assert!(tx.execute(ctx()).await.is_ok());
+
+    tx.value = "baz".as_bytes().to_vec();
+    let resp = tx.execute(ctx()).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::InvalidData);
+}
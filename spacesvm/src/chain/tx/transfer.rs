@@ -0,0 +1,218 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chain::{
+        codec::{Packer, Unpacker},
+        storage,
+        tx::decoder::{create_typed_data, mini_kvvm_domain, MessageValue, Type, TypedData},
+    },
+    genesis::Genesis,
+};
+
+use super::{
+    base,
+    decoder::{TD_ADDRESS, TD_BLOCK_ID, TD_BYTES32, TD_PRICE, TD_TO, TD_UINT64, TD_UNITS},
+    tx::TransactionType,
+    unsigned,
+};
+
+/// Moves units from the balance of the sender to another address.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Tx {
+    pub base_tx: base::Tx,
+
+    /// Recipient of the units.
+    pub to: ethereum_types::Address,
+
+    /// Number of units to transfer.
+    pub units: u64,
+}
+
+impl Tx {
+    pub fn unpack(base_tx: base::Tx, unpacker: &mut Unpacker) -> Result<Self> {
+        Ok(Self {
+            base_tx,
+            to: unpacker.unpack_address()?,
+            units: unpacker.unpack_u64()?,
+        })
+    }
+}
+
+// important to define an unique name of the trait implementation
+#[typetag::serde(name = "transfer")]
+#[tonic::async_trait]
+impl unsigned::Transaction for Tx {
+    async fn get_block_id(&self) -> avalanche_types::ids::Id {
+        self.base_tx.block_id
+    }
+
+    async fn set_block_id(&mut self, id: avalanche_types::ids::Id) {
+        self.base_tx.block_id = id;
+    }
+
+    async fn get_base_tx(&self) -> base::Tx {
+        self.base_tx
+    }
+
+    async fn set_base_tx(&mut self, base_tx: base::Tx) {
+        self.base_tx = base_tx;
+    }
+
+    async fn get_value(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    async fn set_value(&mut self, _value: Vec<u8>) -> std::io::Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "value is not supported for transfer tx",
+        ))
+    }
+
+    async fn typ(&self) -> TransactionType {
+        TransactionType::Transfer
+    }
+
+    async fn fee_units(&self, _genesis: &Genesis) -> u64 {
+        0
+    }
+
+    async fn validate(&self, _genesis: &Genesis) -> Result<()> {
+        Ok(())
+    }
+
+    async fn pack(&self, packer: &mut Packer) {
+        packer.pack_address(&self.to);
+        packer.pack_u64(self.units);
+    }
+
+    async fn execute(&self, txn_ctx: unsigned::TransactionContext) -> Result<()> {
+        let mut db = txn_ctx.db;
+
+        if self.units == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "transfer units must be greater than zero",
+            ));
+        }
+        if self.to.is_zero() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "transfer recipient must not be the zero address",
+            ));
+        }
+
+        storage::sub_balance(&mut db, &txn_ctx.sender, self.units).await?;
+        storage::add_balance(&mut db, &self.to, self.units).await?;
+
+        Ok(())
+    }
+
+    async fn typed_data(&self) -> TypedData {
+        let mut tx_fields: Vec<Type> = Vec::new();
+        tx_fields.push(Type {
+            name: TD_TO.to_owned(),
+            type_: TD_ADDRESS.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_UNITS.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_BLOCK_ID.to_owned(),
+            type_: TD_BYTES32.to_owned(),
+        });
+        tx_fields.push(Type {
+            name: TD_PRICE.to_owned(),
+            type_: TD_UINT64.to_owned(),
+        });
+
+        let mut message: HashMap<String, MessageValue> = HashMap::with_capacity(4);
+        message.insert(
+            TD_TO.to_owned(),
+            MessageValue::Bytes(self.to.as_bytes().to_vec()),
+        );
+        message.insert(TD_UNITS.to_owned(), MessageValue::Uint(self.units));
+        message.insert(
+            TD_BLOCK_ID.to_owned(),
+            MessageValue::Bytes(self.base_tx.block_id.to_vec()),
+        );
+        message.insert(TD_PRICE.to_owned(), MessageValue::Uint(self.base_tx.price));
+
+        return create_typed_data(
+            super::tx::TransactionType::Transfer,
+            tx_fields,
+            message,
+            mini_kvvm_domain(self.base_tx.magic, &self.base_tx.chain_id),
+        );
+    }
+}
+
+#[tokio::test]
+async fn transfer_tx_test() {
+    use super::unsigned::Transaction;
+    use std::str::FromStr;
+
+    let sender =
+        ethereum_types::Address::from_str("0000000000000000000000000000000000000001").unwrap();
+    let recipient =
+        ethereum_types::Address::from_str("0000000000000000000000000000000000000002").unwrap();
+
+    // transfer without funds
+    let mut db = avalanche_types::subnet::rpc::database::memdb::Database::new();
+    let ut_ctx = unsigned::TransactionContext {
+        db: db.clone(),
+        block_time: 0,
+        tx_id: avalanche_types::ids::Id::empty(),
+        sender,
+        cosigners: vec![],
+        genesis: crate::genesis::Genesis::default(),
+        chain_id: avalanche_types::ids::Id::empty(),
+    };
+    let tx = Tx {
+        base_tx: base::Tx::default(),
+        to: recipient,
+        units: 10,
+    };
+    let resp = tx.execute(ut_ctx).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    // fund sender and transfer
+    storage::add_balance(&mut db, &sender, 15).await.unwrap();
+    let ut_ctx = unsigned::TransactionContext {
+        db: db.clone(),
+        block_time: 0,
+        tx_id: avalanche_types::ids::Id::empty(),
+        sender,
+        cosigners: vec![],
+        genesis: crate::genesis::Genesis::default(),
+        chain_id: avalanche_types::ids::Id::empty(),
+    };
+    let resp = tx.execute(ut_ctx).await;
+    assert!(resp.is_ok());
+    assert_eq!(storage::get_balance(&db, &sender).await.unwrap(), 5);
+    assert_eq!(storage::get_balance(&db, &recipient).await.unwrap(), 10);
+
+    // zero units are rejected
+    let ut_ctx = unsigned::TransactionContext {
+        db: db.clone(),
+        block_time: 0,
+        tx_id: avalanche_types::ids::Id::empty(),
+        sender,
+        cosigners: vec![],
+        genesis: crate::genesis::Genesis::default(),
+        chain_id: avalanche_types::ids::Id::empty(),
+    };
+    let tx = Tx {
+        base_tx: base::Tx::default(),
+        to: recipient,
+        units: 0,
+    };
+    let resp = tx.execute(ut_ctx).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::InvalidInput);
+}
@@ -1,15 +1,28 @@
 use std::{
     fmt::{self, Debug},
     io::{Error, ErrorKind, Result},
+    str::FromStr,
 };
 
 use avalanche_types::{hash, ids, key, subnet};
 use ethereum_types::Address;
 use serde::{Deserialize, Serialize};
 
-use crate::{block::Block, chain::storage::set_transaction};
+use crate::{
+    block::Block,
+    chain::{
+        codec, crypto,
+        storage::{self, set_transaction},
+        vm::Context,
+    },
+    genesis::Genesis,
+};
 
-use super::{decoder, unsigned::TransactionContext};
+use super::{
+    decoder::{self, TypedData},
+    multisig,
+    unsigned::TransactionContext,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(tag = "type")]
@@ -20,6 +33,28 @@ pub enum TransactionType {
     Set,
     /// Remove a key.
     Delete,
+    /// Move units between addresses.
+    Transfer,
+    /// Transfer ownership of a space.
+    Move,
+    /// Extend the expiry of a space.
+    Lifeline,
+    /// Apply several set and delete ops to a space at once.
+    Batch,
+    /// Set or delete a key if it still references the expected tx.
+    Cas,
+    /// Allow another address to write keys of a space.
+    Grant,
+    /// Remove the write access of an address.
+    Revoke,
+    /// Make a space owned by a set of addresses.
+    Multisig,
+    /// Release a space before it expires.
+    DeleteSpace,
+    /// Append bytes to the value of a key.
+    Append,
+    /// Add to a counter stored as the value of a key.
+    Increment,
     /// Used for testing only
     Unknown,
 }
@@ -36,16 +71,63 @@ impl fmt::Display for TransactionType {
             TransactionType::Claim => write!(f, "claim"),
             TransactionType::Set => write!(f, "set"),
             TransactionType::Delete => write!(f, "delete"),
+            TransactionType::Transfer => write!(f, "transfer"),
+            TransactionType::Move => write!(f, "move"),
+            TransactionType::Lifeline => write!(f, "lifeline"),
+            TransactionType::Batch => write!(f, "batch"),
+            TransactionType::Cas => write!(f, "cas"),
+            TransactionType::Grant => write!(f, "grant"),
+            TransactionType::Revoke => write!(f, "revoke"),
+            TransactionType::Multisig => write!(f, "multisig"),
+            TransactionType::DeleteSpace => write!(f, "delete_space"),
+            TransactionType::Append => write!(f, "append"),
+            TransactionType::Increment => write!(f, "increment"),
             TransactionType::Unknown => write!(f, "unknown"),
         }
     }
 }
 
+impl FromStr for TransactionType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "claim" => Ok(TransactionType::Claim),
+            "set" => Ok(TransactionType::Set),
+            "delete" => Ok(TransactionType::Delete),
+            "transfer" => Ok(TransactionType::Transfer),
+            "move" => Ok(TransactionType::Move),
+            "lifeline" => Ok(TransactionType::Lifeline),
+            "batch" => Ok(TransactionType::Batch),
+            "cas" => Ok(TransactionType::Cas),
+            "grant" => Ok(TransactionType::Grant),
+            "revoke" => Ok(TransactionType::Revoke),
+            "multisig" => Ok(TransactionType::Multisig),
+            "delete_space" => Ok(TransactionType::DeleteSpace),
+            "append" => Ok(TransactionType::Append),
+            "increment" => Ok(TransactionType::Increment),
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unknown transaction type: {}", s),
+            )),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Transaction {
     pub unsigned_transaction: Box<dyn super::unsigned::Transaction + Send + Sync>,
     pub signature: Vec<u8>,
 
+    /// Addresses which cosign the tx to act as the multisig owner of a
+    /// space. They are part of the typed data everyone signs.
+    #[serde(default)]
+    pub cosigners: Vec<Address>,
+
+    /// Signatures of [cosigners] over the same typed data, in the same order.
+    #[serde(default)]
+    pub cosignatures: Vec<Vec<u8>>,
+
     #[serde(skip)]
     pub digest_hash: Vec<u8>,
 
@@ -70,6 +152,8 @@ impl Transaction {
         Self {
             unsigned_transaction,
             signature,
+            cosigners: vec![],
+            cosignatures: vec![],
             digest_hash: vec![],
             bytes: vec![],
             id: ids::Id::empty(),
@@ -77,27 +161,102 @@ impl Transaction {
             sender: Address::zero(),
         }
     }
+
+    /// Returns the typed data the sender and the cosigners sign.
+    pub async fn typed_data(&self) -> TypedData {
+        self.unsigned_transaction
+            .typed_data()
+            .await
+            .with_cosigners(&self.cosigners)
+    }
+
+    /// Returns the number of units consumed by the transaction. Every
+    /// transaction pays the base units, one unit for every full
+    /// [value_unit_size] bytes of its size and value, and the units specific
+    /// to its type.
+    pub async fn units(&self, genesis: &Genesis) -> u64 {
+        let unit_size = genesis.value_unit_size.max(1);
+        let mut units = genesis.base_tx_units.saturating_add(self.size / unit_size);
+        if let Some(value) = self.unsigned_transaction.get_value().await {
+            units = units.saturating_add(value.len() as u64 / unit_size);
+        }
+        units.saturating_add(self.unsigned_transaction.fee_units(genesis).await)
+    }
 }
 
 #[typetag::serde]
 #[tonic::async_trait]
 impl crate::chain::tx::Transaction for Transaction {
     async fn init(&mut self) -> Result<()> {
-        let stx =
-            serde_json::to_vec(&self).map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+        // ids are computed over the canonical signature so encoding variants
+        // of the same signature can not be included twice
+        self.signature = crypto::normalize_signature(&self.signature)?;
+        if self.cosigners.len() > multisig::MAX_MEMBERS {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "too many cosigners: {} (max: {})",
+                    self.cosigners.len(),
+                    multisig::MAX_MEMBERS
+                ),
+            ));
+        }
+        if self.cosignatures.len() != self.cosigners.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} cosignatures for {} cosigners",
+                    self.cosignatures.len(),
+                    self.cosigners.len()
+                ),
+            ));
+        }
+        for cosignature in self.cosignatures.iter_mut() {
+            *cosignature = crypto::normalize_signature(cosignature)?;
+        }
+        let stx = codec::encode_tx(self).await?;
+        let id_bytes = codec::encode_tx_for_id(self).await?;
 
-        let typed_data = &self.unsigned_transaction.typed_data().await;
+        // the cosigners are part of the typed data, so cosignatures can not
+        // be stripped or added without changing the sender
+        let typed_data = &self.typed_data().await;
         let digest_hash = decoder::hash_structured_data(typed_data)?;
 
         let sender = key::secp256k1::public_key::Key::from_signature(
             digest_hash.as_bytes(),
             &self.signature,
-        )?;
+        )?
+        .to_h160();
+        for (i, (cosigner, cosignature)) in self
+            .cosigners
+            .iter()
+            .zip(self.cosignatures.iter())
+            .enumerate()
+        {
+            if *cosigner == sender || self.cosigners[..i].contains(cosigner) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("duplicate signer: {:?}", cosigner),
+                ));
+            }
+            let signer = key::secp256k1::public_key::Key::from_signature(
+                digest_hash.as_bytes(),
+                cosignature,
+            )?
+            .to_h160();
+            if signer != *cosigner {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("invalid cosignature of {:?}", cosigner),
+                ));
+            }
+        }
+
         self.bytes = stx;
-        self.id = ids::Id::from_slice(hash::keccak256(&self.bytes).as_bytes());
+        self.id = ids::Id::from_slice(hash::keccak256(&id_bytes).as_bytes());
         self.size = self.bytes.len() as u64;
         self.digest_hash = digest_hash.as_bytes().to_vec();
-        self.sender = sender.to_h160();
+        self.sender = sender;
 
         Ok(())
     }
@@ -118,6 +277,7 @@ impl crate::chain::tx::Transaction for Transaction {
         &self,
         db: &'life1 Box<dyn subnet::rpc::database::Database + Send + Sync>,
         block: &Block,
+        context: &Context,
     ) -> Result<()> {
         log::debug!("execute: sender: {}", self.sender);
         let txn_ctx = TransactionContext {
@@ -125,12 +285,41 @@ impl crate::chain::tx::Transaction for Transaction {
             tx_id: self.id,
             block_time: block.timestamp,
             sender: self.sender,
+            cosigners: self.cosigners.clone(),
+            genesis: block.state.get_genesis().await,
+            chain_id: block.state.get_chain_id().await,
         };
+        self.unsigned_transaction.validate(&txn_ctx.genesis).await?;
 
-        self.unsigned_transaction
-            .execute(txn_ctx)
+        let base_tx = self.unsigned_transaction.get_base_tx().await;
+        base_tx.execute_base(&txn_ctx, context).await?;
+
+        // fees are burned
+        let genesis = &txn_ctx.genesis;
+        if base_tx.price < genesis.min_price {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "price too low: {} (minimum: {})",
+                    base_tx.price, genesis.min_price
+                ),
+            ));
+        }
+        let fee = self
+            .units(genesis)
             .await
-            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+            .checked_mul(base_tx.price)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("fee overflow: {}", self.id),
+                )
+            })?;
+        let mut fee_db = db.clone();
+        storage::sub_balance(&mut fee_db, &self.sender, fee).await?;
+
+        // passed on as is so callers can match the kind and inner error
+        self.unsigned_transaction.execute(txn_ctx).await?;
 
         log::debug!("execute: set tx");
         set_transaction(db.clone(), self.to_owned())
@@ -151,10 +340,278 @@ pub fn new_tx(
         signature,
 
         // defaults
+        cosignatures: vec![],
         digest_hash: vec![],
         bytes: vec![],
         id: ids::Id::empty(),
         size: 0,
         sender: Address::zero(),
+        cosigners: vec![],
+    }
+}
+
+#[tokio::test]
+async fn test_execute() {
+    use crate::{
+        block::{state::State, Block},
+        chain::{
+            tx::{unsigned::TransactionData, Transaction as _},
+            vm::Context,
+        },
+    };
+
+    let genesis = Genesis {
+        min_price: 2,
+        ..Default::default()
+    };
+    let mut db = subnet::rpc::database::memdb::Database::new();
+    let chain_id = ids::Id::from_slice(&[1u8; ids::LEN]);
+    let state = State::new(db.clone(), genesis.clone(), chain_id);
+    let secret_key = key::secp256k1::private_key::Key::generate().unwrap();
+    let sender = secret_key.to_public_key().to_h160();
+
+    let block_id = ids::Id::from_slice(&[2u8; ids::LEN]);
+    let mut context = Context::default();
+    context.recent_block_ids.insert(block_id);
+
+    let signed_tx = |price: u64, chain_id: ids::Id| {
+        let secret_key = secret_key.clone();
+        let magic = genesis.magic;
+        async move {
+            let mut utx = TransactionData {
+                typ: TransactionType::Claim,
+                space: "foo".to_string(),
+                ..Default::default()
+            }
+            .decode()
+            .unwrap();
+            let mut base_tx = utx.get_base_tx().await;
+            base_tx.block_id = block_id;
+            base_tx.price = price;
+            base_tx.magic = magic;
+            base_tx.chain_id = chain_id;
+            utx.set_base_tx(base_tx).await;
+
+            let dh = decoder::hash_structured_data(&utx.typed_data().await).unwrap();
+            let sig = secret_key.sign_digest(dh.as_bytes()).unwrap();
+            let mut tx = Transaction::new(utx, sig.to_bytes().to_vec());
+            tx.init().await.unwrap();
+            tx
+        }
+    };
+
+    // claims pay for their size and the claim units
+    let tx = signed_tx(2, chain_id).await;
+    let units = tx.units(&genesis).await;
+    assert_eq!(
+        units,
+        genesis.base_tx_units + tx.size / genesis.value_unit_size + genesis.claim_units
+    );
+
+    // price below the genesis minimum
+    let low = signed_tx(1, chain_id).await;
+    let block = Block::new_dummy(0, low.clone(), state.clone());
+    let resp = low.execute(&db, &block, &context).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    // signed for another chain
+    let replay = signed_tx(2, ids::Id::empty()).await;
+    let block = Block::new_dummy(0, replay.clone(), state.clone());
+    let resp = replay.execute(&db, &block, &context).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::InvalidData);
+
+    // sender can not pay the fee
+    let block = Block::new_dummy(0, tx.clone(), state.clone());
+    assert!(tx.execute(&db, &block, &context).await.is_err());
+
+    // fee is debited from the sender
+    storage::add_balance(&mut db, &sender, units * 2 + 5)
+        .await
+        .unwrap();
+    assert!(tx.execute(&db, &block, &context).await.is_ok());
+    assert_eq!(storage::get_balance(&db, &sender).await.unwrap(), 5);
+
+    // tx can not be included twice within the lookback window
+    context.recent_tx_ids.insert(tx.id);
+    let resp = tx.execute(&db, &block, &context).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::AlreadyExists);
+
+    // tx expires with its block
+    let resp = tx.execute(&db, &block, &Context::default()).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::InvalidData);
+}
+
+#[tokio::test]
+async fn test_signature_variants() {
+    use crate::chain::tx::{unsigned::TransactionData, Transaction as _};
+
+    let secret_key = key::secp256k1::private_key::Key::generate().unwrap();
+    let utx = TransactionData {
+        typ: TransactionType::Claim,
+        space: "foo".to_string(),
+        ..Default::default()
+    }
+    .decode()
+    .unwrap();
+    let dh = decoder::hash_structured_data(&utx.typed_data().await).unwrap();
+    let sig = secret_key
+        .sign_digest(dh.as_bytes())
+        .unwrap()
+        .to_bytes()
+        .to_vec();
+
+    let mut tx = Transaction::new(utx.clone(), sig.clone());
+    tx.init().await.unwrap();
+
+    // wallet style recovery ids yield the same tx
+    let mut wallet_sig = sig.clone();
+    wallet_sig[64] += 27;
+    let mut wallet_tx = Transaction::new(utx.clone(), wallet_sig);
+    wallet_tx.init().await.unwrap();
+    assert_eq!(wallet_tx.id, tx.id);
+    assert_eq!(wallet_tx.sender, tx.sender);
+    assert_eq!(wallet_tx.signature, sig);
+
+    // the high s twin of the signature is rejected
+    let order = [
+        0xffu8, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xfe, 0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36,
+        0x41, 0x41,
+    ];
+    let mut high_sig = sig.clone();
+    let mut borrow = 0i16;
+    for i in (0..32).rev() {
+        let d = order[i] as i16 - sig[32 + i] as i16 - borrow;
+        borrow = if d < 0 { 1 } else { 0 };
+        high_sig[32 + i] = (d + 256 * borrow) as u8;
+    }
+    high_sig[64] ^= 1;
+    let mut high_tx = Transaction::new(utx, high_sig);
+    assert_eq!(
+        high_tx.init().await.unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+}
+
+#[tokio::test]
+async fn test_cosignatures() {
+    use crate::chain::tx::{unsigned::TransactionData, Transaction as _};
+
+    let sender_key = key::secp256k1::private_key::Key::generate().unwrap();
+    let cosigner_key = key::secp256k1::private_key::Key::generate().unwrap();
+    let utx = TransactionData {
+        typ: TransactionType::Set,
+        space: "kvs".to_string(),
+        key: "foo".to_string(),
+        value: "bar".as_bytes().to_vec(),
+        ..Default::default()
     }
+    .decode()
+    .unwrap();
+    let sender = sender_key.to_public_key().to_h160();
+    let cosigner = cosigner_key.to_public_key().to_h160();
+
+    // everyone signs the typed data including the cosigners
+    let typed_data = utx.typed_data().await;
+    let sign = |key: &key::secp256k1::private_key::Key, cosigners: &[Address]| {
+        let typed_data = typed_data.clone().with_cosigners(cosigners);
+        let dh = decoder::hash_structured_data(&typed_data).unwrap();
+        key.sign_digest(dh.as_bytes()).unwrap().to_bytes().to_vec()
+    };
+    let cosigned = |sender_sig: Vec<u8>, cosigners: Vec<Address>, cosignatures: Vec<Vec<u8>>| {
+        let mut tx = Transaction::new(utx.clone(), sender_sig);
+        tx.cosigners = cosigners;
+        tx.cosignatures = cosignatures;
+        tx
+    };
+
+    let mut single = Transaction::new(utx.clone(), sign(&sender_key, &[]));
+    single.init().await.unwrap();
+    assert!(single.cosigners.is_empty());
+    assert_eq!(single.sender, sender);
+
+    let mut tx = cosigned(
+        sign(&sender_key, &[cosigner]),
+        vec![cosigner],
+        vec![sign(&cosigner_key, &[cosigner])],
+    );
+    tx.init().await.unwrap();
+    assert_eq!(tx.sender, sender);
+    assert_eq!(tx.cosigners, vec![cosigner]);
+    assert_ne!(tx.id, single.id);
+    assert!(tx.size > single.size);
+
+    // the cosigners are a regular field of the typed data, as signed by
+    // eth_signTypedData_v4
+    let json = serde_json::to_string(&tx.typed_data().await).unwrap();
+    let parsed: TypedData = serde_json::from_str(&json).unwrap();
+    assert!(parsed.types[&TransactionType::Set.to_string()]
+        .iter()
+        .any(|field| field.name == decoder::TD_COSIGNERS && field.type_ == "address[]"));
+    assert_eq!(parsed.parse_cosigners().unwrap(), vec![cosigner]);
+    assert_eq!(
+        decoder::hash_structured_data(&parsed).unwrap().as_bytes(),
+        &tx.digest_hash[..]
+    );
+
+    // stripping the cosigners changes the sender and the id
+    let mut stripped = tx.clone();
+    stripped.cosigners.clear();
+    stripped.cosignatures.clear();
+    stripped.init().await.unwrap();
+    assert_ne!(stripped.sender, sender);
+    assert_ne!(stripped.id, tx.id);
+
+    // adding one invalidates the cosignatures over the previous cosigners
+    let other_key = key::secp256k1::private_key::Key::generate().unwrap();
+    let other = other_key.to_public_key().to_h160();
+    let mut added = tx.clone();
+    added.cosigners.push(other);
+    added
+        .cosignatures
+        .push(sign(&other_key, &[cosigner, other]));
+    assert_eq!(
+        added.init().await.unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+
+    // every cosigner must sign
+    let mut unsigned = tx.clone();
+    unsigned.cosignatures.clear();
+    assert_eq!(
+        unsigned.init().await.unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+    let mut forged = cosigned(
+        sign(&sender_key, &[cosigner]),
+        vec![cosigner],
+        vec![sign(&other_key, &[cosigner])],
+    );
+    assert_eq!(
+        forged.init().await.unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+
+    // every signer is only counted once
+    let mut dup = cosigned(
+        sign(&sender_key, &[sender]),
+        vec![sender],
+        vec![sign(&sender_key, &[sender])],
+    );
+    assert_eq!(
+        dup.init().await.unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+    let mut dup = cosigned(
+        sign(&sender_key, &[cosigner, cosigner]),
+        vec![cosigner, cosigner],
+        vec![
+            sign(&cosigner_key, &[cosigner, cosigner]),
+            sign(&cosigner_key, &[cosigner, cosigner]),
+        ],
+    );
+    assert_eq!(
+        dup.init().await.unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
 }
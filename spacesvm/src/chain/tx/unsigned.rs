@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     fmt::Debug,
     io::{Error, ErrorKind, Result},
 };
@@ -7,20 +8,39 @@ use avalanche_types::{ids::Id, subnet};
 use dyn_clone::DynClone;
 use serde::{Deserialize, Serialize};
 
-use crate::chain::tx::decoder::TypedData;
+use crate::{
+    chain::{codec::Packer, tx::decoder::TypedData},
+    genesis::Genesis,
+};
 
-use super::{base, claim, delete, set, tx::TransactionType};
+use super::{
+    append, base, batch, cas, claim, delete, delete_space, grant, increment, lifeline, move_space,
+    multisig, revoke, set, transfer, tx::TransactionType,
+};
 
 #[typetag::serde(tag = "type")]
 #[tonic::async_trait]
 pub trait Transaction: Debug + DynClone + Send + Sync {
     async fn get_block_id(&self) -> Id;
     async fn set_block_id(&mut self, id: Id);
+    async fn get_base_tx(&self) -> base::Tx;
+    async fn set_base_tx(&mut self, base_tx: base::Tx);
     async fn get_value(&self) -> Option<Vec<u8>>;
     async fn set_value(&mut self, value: Vec<u8>) -> Result<()>;
+    /// Returns true if the transaction uses fields the Go SpacesVM does not
+    /// know, which are packed under a separate type id.
+    async fn is_extended(&self) -> bool {
+        false
+    }
     async fn execute(&self, txn_ctx: TransactionContext) -> Result<()>;
     async fn typed_data(&self) -> TypedData;
     async fn typ(&self) -> TransactionType;
+    /// Units charged on top of the size based units of every transaction.
+    async fn fee_units(&self, genesis: &Genesis) -> u64;
+    /// Checks the format of the names, keys and values of the transaction.
+    async fn validate(&self, genesis: &Genesis) -> Result<()>;
+    /// Packs the fields which follow the base tx in the canonical encoding.
+    async fn pack(&self, packer: &mut Packer);
 }
 
 // ref. https://docs.rs/dyn-clone/latest/dyn_clone/macro.clone_trait_object.html
@@ -31,14 +51,75 @@ pub struct TransactionContext {
     pub block_time: u64,
     pub tx_id: Id,
     pub sender: ethereum_types::Address,
+    /// Addresses which signed the tx in addition to the sender.
+    pub cosigners: Vec<ethereum_types::Address>,
+    pub genesis: Genesis,
+    pub chain_id: Id,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg(test)]
+impl TransactionContext {
+    /// Returns a context executing a tx of [sender] on [db] at [block_time]
+    /// with the default genesis. Every byte of the tx id is [tx_id].
+    pub fn for_test(
+        db: Box<dyn subnet::rpc::database::Database + Send + Sync>,
+        sender: ethereum_types::Address,
+        block_time: u64,
+        tx_id: u8,
+    ) -> Self {
+        Self {
+            db,
+            block_time,
+            tx_id: Id::from_slice(&[tx_id; avalanche_types::ids::LEN]),
+            sender,
+            cosigners: vec![],
+            genesis: Genesis::default(),
+            chain_id: Id::empty(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct TransactionData {
     pub typ: TransactionType,
     pub space: String,
     pub key: String,
     pub value: Vec<u8>,
+    #[serde(default)]
+    pub to: ethereum_types::Address,
+    #[serde(default)]
+    pub units: u64,
+    #[serde(default)]
+    pub ops: Vec<batch::Op>,
+    /// Tx id a cas tx expects the key to reference, empty if it must not
+    /// exist.
+    #[serde(default)]
+    pub expected: Id,
+    /// Makes a cas tx delete the key instead of setting it.
+    #[serde(default)]
+    pub delete: bool,
+    /// Key prefix a grant is scoped to.
+    #[serde(default)]
+    pub prefix: String,
+    /// Unix timestamp at which a grant or the key of a set expires, zero if
+    /// it does not.
+    #[serde(default)]
+    pub expiry: u64,
+    /// Number of members which must sign for a multisig space.
+    #[serde(default)]
+    pub threshold: u64,
+    /// Addresses owning a multisig space.
+    #[serde(default)]
+    pub members: Vec<ethereum_types::Address>,
+    /// MIME type of the value of a set.
+    #[serde(default)]
+    pub content_type: String,
+    /// User defined attributes of the value of a set.
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+    /// Added to the counter of an increment.
+    #[serde(default)]
+    pub amount: u64,
 }
 
 impl TransactionData {
@@ -54,12 +135,82 @@ impl TransactionData {
                 space: tx_param.space,
                 key: tx_param.key,
                 value: tx_param.value,
+                expiry: tx_param.expiry,
+                content_type: tx_param.content_type,
+                attributes: tx_param.attributes,
             })),
             TransactionType::Delete => Ok(Box::new(delete::Tx {
                 base_tx: base::Tx::default(),
                 space: tx_param.space,
                 key: tx_param.key,
             })),
+            TransactionType::Transfer => Ok(Box::new(transfer::Tx {
+                base_tx: base::Tx::default(),
+                to: tx_param.to,
+                units: tx_param.units,
+            })),
+            TransactionType::Move => Ok(Box::new(move_space::Tx {
+                base_tx: base::Tx::default(),
+                space: tx_param.space,
+                to: tx_param.to,
+            })),
+            TransactionType::Lifeline => Ok(Box::new(lifeline::Tx {
+                base_tx: base::Tx::default(),
+                space: tx_param.space,
+                units: tx_param.units,
+            })),
+            TransactionType::Batch => Ok(Box::new(batch::Tx {
+                base_tx: base::Tx::default(),
+                space: tx_param.space,
+                ops: tx_param.ops,
+            })),
+            TransactionType::Cas => Ok(Box::new(cas::Tx {
+                base_tx: base::Tx::default(),
+                space: tx_param.space,
+                expected: tx_param.expected,
+                op: if tx_param.delete {
+                    batch::Op::Delete { key: tx_param.key }
+                } else {
+                    batch::Op::Set {
+                        key: tx_param.key,
+                        value: tx_param.value,
+                    }
+                },
+            })),
+            TransactionType::Grant => Ok(Box::new(grant::Tx {
+                base_tx: base::Tx::default(),
+                space: tx_param.space,
+                to: tx_param.to,
+                prefix: tx_param.prefix,
+                expiry: tx_param.expiry,
+            })),
+            TransactionType::Revoke => Ok(Box::new(revoke::Tx {
+                base_tx: base::Tx::default(),
+                space: tx_param.space,
+                to: tx_param.to,
+            })),
+            TransactionType::Multisig => Ok(Box::new(multisig::Tx {
+                base_tx: base::Tx::default(),
+                space: tx_param.space,
+                threshold: tx_param.threshold,
+                members: tx_param.members,
+            })),
+            TransactionType::DeleteSpace => Ok(Box::new(delete_space::Tx {
+                base_tx: base::Tx::default(),
+                space: tx_param.space,
+            })),
+            TransactionType::Append => Ok(Box::new(append::Tx {
+                base_tx: base::Tx::default(),
+                space: tx_param.space,
+                key: tx_param.key,
+                value: tx_param.value,
+            })),
+            TransactionType::Increment => Ok(Box::new(increment::Tx {
+                base_tx: base::Tx::default(),
+                space: tx_param.space,
+                key: tx_param.key,
+                amount: tx_param.amount,
+            })),
             TransactionType::Unknown => Err(Error::new(
                 ErrorKind::Other,
                 "transaction type Unknown is not valid",
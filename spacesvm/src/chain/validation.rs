@@ -0,0 +1,316 @@
+use std::{
+    collections::BTreeMap,
+    io::{Error, ErrorKind, Result},
+};
+
+use unicode_general_category::{get_general_category, GeneralCategory};
+use unicode_normalization::is_nfc;
+
+use crate::genesis::Genesis;
+
+/// Maximum length of a space name in bytes.
+pub const MAX_SPACE_LEN: usize = 256;
+
+/// Maximum length of a key in bytes.
+pub const MAX_KEY_LEN: usize = 256;
+
+/// Maximum length of the content type of a value in bytes.
+pub const MAX_CONTENT_TYPE_LEN: usize = 256;
+
+/// Maximum number of attributes of a value.
+pub const MAX_ATTRIBUTES: usize = 16;
+
+/// Maximum length of an attribute name in bytes.
+pub const MAX_ATTRIBUTE_NAME_LEN: usize = 64;
+
+/// Maximum length of an attribute value in bytes.
+pub const MAX_ATTRIBUTE_VALUE_LEN: usize = 256;
+
+/// Checks that a space name only uses lowercase ASCII letters, digits, '-'
+/// and '_'. Space names are compared byte for byte so any other character
+/// could be used to create look-alike spaces.
+pub fn check_space(space: &str) -> Result<()> {
+    if space.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "space is empty"));
+    }
+    if space.len() > MAX_SPACE_LEN {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "space too long: {} bytes (max: {})",
+                space.len(),
+                MAX_SPACE_LEN
+            ),
+        ));
+    }
+    if let Some(c) = space
+        .chars()
+        .find(|c| !matches!(c, 'a'..='z' | '0'..='9' | '-' | '_'))
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "invalid character {:?} in space {:?}: only a-z, 0-9, '-' and '_' are allowed",
+                c, space
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that a space may be claimed. Reserved names can not be claimed by
+/// anyone.
+pub fn check_claim(space: &str, genesis: &Genesis) -> Result<()> {
+    check_space(space)?;
+    if genesis.reserved_spaces.iter().any(|r| r == space) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("space is reserved: {}", space),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that a key is NFC normalized and free of '/', whitespace and
+/// invisible characters, so every key has a single visible spelling.
+pub fn check_key(key: &str) -> Result<()> {
+    if key.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "key is empty"));
+    }
+    if key.len() > MAX_KEY_LEN {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("key too long: {} bytes (max: {})", key.len(), MAX_KEY_LEN),
+        ));
+    }
+    if let Some(c) = key
+        .chars()
+        .find(|c| *c == '/' || c.is_whitespace() || c.is_control() || is_format(*c))
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid character {:?} in key {:?}", c, key),
+        ));
+    }
+    if !is_nfc(key) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("key is not NFC normalized: {:?}", key),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks the size of a value against the genesis limit.
+pub fn check_value(value: &[u8], genesis: &Genesis) -> Result<()> {
+    if value.len() as u64 > genesis.max_value_size {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "value too large: {} bytes (max: {})",
+                value.len(),
+                genesis.max_value_size
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that a content type is a printable ASCII MIME type of the form
+/// "type/subtype", optionally followed by parameters.
+pub fn check_content_type(content_type: &str) -> Result<()> {
+    if content_type.len() > MAX_CONTENT_TYPE_LEN {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "content type too long: {} bytes (max: {})",
+                content_type.len(),
+                MAX_CONTENT_TYPE_LEN
+            ),
+        ));
+    }
+    if let Some(c) = content_type.chars().find(|c| !matches!(c, ' '..='~')) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "invalid character {:?} in content type {:?}",
+                c, content_type
+            ),
+        ));
+    }
+    let essence = content_type.split(';').next().unwrap_or_default();
+    match essence.split_once('/') {
+        Some((typ, subtype))
+            if !typ.trim().is_empty() && !subtype.trim().is_empty() && !subtype.contains('/') =>
+        {
+            Ok(())
+        }
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid content type: {:?}", content_type),
+        )),
+    }
+}
+
+/// Checks the number and size of the attributes of a value. Names follow the
+/// rules of space names, values must not contain control characters.
+pub fn check_attributes(attributes: &BTreeMap<String, String>) -> Result<()> {
+    if attributes.len() > MAX_ATTRIBUTES {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "too many attributes: {} (max: {})",
+                attributes.len(),
+                MAX_ATTRIBUTES
+            ),
+        ));
+    }
+    for (name, value) in attributes.iter() {
+        if name.is_empty()
+            || name.len() > MAX_ATTRIBUTE_NAME_LEN
+            || name
+                .chars()
+                .any(|c| !matches!(c, 'a'..='z' | '0'..='9' | '-' | '_'))
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "invalid attribute name {:?}: 1 to {} characters of a-z, 0-9, '-' and '_' are allowed",
+                    name, MAX_ATTRIBUTE_NAME_LEN
+                ),
+            ));
+        }
+        if value.len() > MAX_ATTRIBUTE_VALUE_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "attribute {:?} too long: {} bytes (max: {})",
+                    name,
+                    value.len(),
+                    MAX_ATTRIBUTE_VALUE_LEN
+                ),
+            ));
+        }
+        if let Some(c) = value.chars().find(|c| c.is_control() || is_format(*c)) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid character {:?} in attribute {:?}", c, name),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns true for the invisible format characters (Unicode category Cf)
+/// such as zero width spaces, joiners and bidi controls.
+fn is_format(c: char) -> bool {
+    get_general_category(c) == GeneralCategory::Format
+}
+
+#[test]
+fn test_check_space() {
+    assert!(check_space("kvs").is_ok());
+    assert!(check_space("my-space_01").is_ok());
+    assert!(check_space(&"a".repeat(MAX_SPACE_LEN)).is_ok());
+
+    for space in [
+        "",
+        "KVS",
+        "k/v",
+        "k v",
+        "kvs\u{200B}",
+        "kv\u{0455}",
+        &"a".repeat(MAX_SPACE_LEN + 1),
+    ] {
+        let err = check_space(space).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput, "{:?}", space);
+    }
+
+    let genesis = Genesis::default();
+    assert!(check_claim("kvs", &genesis).is_ok());
+    let err = check_claim("spacesvm", &genesis).unwrap_err();
+    assert!(err.to_string().contains("reserved"));
+}
+
+#[test]
+fn test_check_key() {
+    assert!(check_key("foo").is_ok());
+    assert!(check_key("foo.bar-baz_1").is_ok());
+    assert!(check_key("caf\u{00E9}").is_ok());
+    assert!(check_key(&format!("0x{}", "ab".repeat(32))).is_ok());
+
+    for key in [
+        "",
+        "foo/bar",
+        "foo bar",
+        "foo\n",
+        "foo\u{200B}",
+        "\u{FEFF}foo",
+        "\u{202E}oof",
+        "foo\u{110BD}",
+        // "é" as "e" followed by a combining accent
+        "cafe\u{0301}",
+        &"a".repeat(MAX_KEY_LEN + 1),
+    ] {
+        let err = check_key(key).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput, "{:?}", key);
+    }
+}
+
+#[test]
+fn test_check_value() {
+    let genesis = Genesis {
+        max_value_size: 4,
+        ..Default::default()
+    };
+    assert!(check_value(&[0; 4], &genesis).is_ok());
+    assert!(check_value(&[0; 5], &genesis).is_err());
+}
+
+#[test]
+fn test_check_content_type() {
+    assert!(check_content_type("application/json").is_ok());
+    assert!(check_content_type("text/plain; charset=utf-8").is_ok());
+    assert!(check_content_type("image/svg+xml").is_ok());
+
+    for content_type in [
+        "",
+        "json",
+        "/json",
+        "text/",
+        "a/b/c",
+        "text/pl\u{00E4}in",
+        "text/plain\n",
+    ] {
+        assert!(
+            check_content_type(content_type).is_err(),
+            "{:?}",
+            content_type
+        );
+    }
+    assert!(check_content_type(&format!("text/{}", "a".repeat(MAX_CONTENT_TYPE_LEN))).is_err());
+}
+
+#[test]
+fn test_check_attributes() {
+    let attributes =
+        |name: &str, value: &str| BTreeMap::from([(name.to_owned(), value.to_owned())]);
+    assert!(check_attributes(&BTreeMap::new()).is_ok());
+    assert!(check_attributes(&attributes("author", "Jane Doe")).is_ok());
+    assert!(check_attributes(&attributes("encoding", "")).is_ok());
+
+    assert!(check_attributes(&attributes("", "x")).is_err());
+    assert!(check_attributes(&attributes("Author", "x")).is_err());
+    assert!(check_attributes(&attributes("a=b", "x")).is_err());
+    assert!(check_attributes(&attributes("author", "a\nb")).is_err());
+    assert!(check_attributes(&attributes(
+        "author",
+        &"a".repeat(MAX_ATTRIBUTE_VALUE_LEN + 1)
+    ))
+    .is_err());
+
+    let many: BTreeMap<String, String> = (0..=MAX_ATTRIBUTES)
+        .map(|i| (format!("a{}", i), String::new()))
+        .collect();
+    assert!(check_attributes(&many).is_err());
+}
@@ -4,6 +4,8 @@ use std::io::Result;
 
 use super::tx::tx::Transaction;
 
+/// Recent history of the chain which transactions are executed against.
+#[derive(Debug, Default)]
 pub struct Context {
     pub recent_block_ids: ids::Set,
     pub recent_tx_ids: ids::Set,
@@ -5,13 +5,69 @@ use std::{
     path::Path,
 };
 
+use avalanche_types::subnet;
 use log::info;
 use serde::{Deserialize, Serialize};
 
+use crate::chain::storage;
+
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+#[serde(default)]
 pub struct Genesis {
     pub author: String,
     pub welcome_message: String,
+
+    /// Network magic signed into every transaction so that transactions of
+    /// one network can not be replayed on another.
+    pub magic: u64,
+
+    /// Number of seconds a claimed space stays alive before it must be
+    /// renewed with a lifeline.
+    pub claim_reward: u64,
+
+    /// Number of recent blocks a transaction may reference with its block
+    /// id. Transactions can not be included again within this window.
+    pub lookback_window: u64,
+
+    /// Minimum price per unit a transaction must pay.
+    pub min_price: u64,
+
+    /// Units charged for every transaction.
+    pub base_tx_units: u64,
+
+    /// Number of bytes of tx size and value charged as one unit.
+    pub value_unit_size: u64,
+
+    /// Additional units charged for claiming a space.
+    pub claim_units: u64,
+
+    /// Additional units charged for each period a lifeline extends a space.
+    pub lifeline_units: u64,
+
+    /// Maximum size of a value in bytes.
+    pub max_value_size: u64,
+
+    /// Maximum number of keys stored in a single space.
+    pub max_space_keys: u64,
+
+    /// Maximum number of bytes of keys and values stored in a single space.
+    pub max_space_bytes: u64,
+
+    /// Maximum number of bytes of values stored in a single space.
+    pub max_space_stored_bytes: u64,
+
+    /// Space names which can not be claimed.
+    pub reserved_spaces: Vec<String>,
+
+    /// Initial balances credited to addresses when the chain is created.
+    pub custom_allocation: Vec<CustomAllocation>,
+}
+
+/// Units allocated to an address at genesis.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub struct CustomAllocation {
+    pub address: ethereum_types::Address,
+    pub balance: u64,
 }
 
 impl Default for Genesis {
@@ -25,9 +81,39 @@ impl Genesis {
         Self {
             author: String::from("subnet creator"),
             welcome_message: String::from("Hello from Rust VM!"),
+            magic: 1,
+            claim_reward: 60 * 60 * 24 * 30, // 30 days
+            lookback_window: 64,
+            min_price: 1,
+            base_tx_units: 1,
+            value_unit_size: 1024, // 1 KiB
+            claim_units: 10,
+            lifeline_units: 1,
+            max_value_size: 200 * 1024, // 200 KiB
+            max_space_keys: 10_000,
+            max_space_bytes: 100 * 1024 * 1024,         // 100 MiB
+            max_space_stored_bytes: 1024 * 1024 * 1024, // 1 GiB
+            reserved_spaces: vec![
+                String::from("spacesvm"),
+                String::from("admin"),
+                String::from("root"),
+                String::from("system"),
+            ],
+            custom_allocation: Vec::new(),
         }
     }
 
+    /// Seeds the database with the state described by the genesis.
+    pub async fn load(
+        &self,
+        db: &mut Box<dyn subnet::rpc::database::Database + Send + Sync>,
+    ) -> io::Result<()> {
+        for alloc in self.custom_allocation.iter() {
+            storage::add_balance(db, &alloc.address, alloc.balance).await?;
+        }
+        Ok(())
+    }
+
     pub fn from_json<S>(d: S) -> io::Result<Self>
     where
         S: AsRef<[u8]>,
@@ -266,6 +266,7 @@ async fn test_mempool() {
         space: "foo".to_string(),
         key: "".to_string(),
         value: vec![],
+        ..Default::default()
     };
     let resp = tx_data_1.decode();
     assert!(resp.is_ok());
@@ -292,6 +293,7 @@ async fn test_mempool() {
         space: "bar".to_string(),
         key: "".to_string(),
         value: vec![],
+        ..Default::default()
     };
     let resp = tx_data_2.decode();
     assert!(resp.is_ok());
@@ -336,6 +338,7 @@ async fn test_mempool_threads() {
             space: "foo".to_string(),
             key: "".to_string(),
             value: vec![],
+            ..Default::default()
         };
         let resp = tx_data_1.decode();
         assert!(resp.is_ok());
@@ -211,10 +211,12 @@ impl subnet::rpc::common::vm::Vm for ChainVm {
         let db = current.db.clone();
         let genesis = Genesis::from_json(genesis_bytes)?;
 
+        let chain_id = ctx.as_ref().expect("ctx").chain_id;
+
         vm.ctx = ctx;
         vm.to_engine = Some(to_engine);
         vm.app_sender = Some(app_sender);
-        vm.state = block::state::State::new(db);
+        vm.state = block::state::State::new(db, genesis.clone(), chain_id);
         vm.genesis = genesis;
         self.node_id = vm.ctx.as_ref().expect("inner.ctx").node_id;
 
@@ -227,20 +229,19 @@ impl subnet::rpc::common::vm::Vm for ChainVm {
 
         // Check if last accepted block exists
         if has {
+            // accepted blocks are written in a single batch, so the last
+            // accepted block is always fully stored
             let block_id = vm
                 .state
                 .get_last_accepted()
                 .await
                 .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
-
-            let mut block = vm
-                .state
+            vm.state
                 .get_block(block_id)
                 .await
                 .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
 
             vm.preferred = block_id;
-            vm.state.set_last_accepted(&mut block).await?;
             log::debug!("initialized vm from last accepted block id: {:?}", block_id)
         } else {
             let mut genesis_block =
@@ -256,7 +257,16 @@ impl subnet::rpc::common::vm::Vm for ChainVm {
                 .await
                 .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
 
+            // allocations are written in the same batch as the genesis block
             let genesis_block_id = genesis_block.id;
+            let overlay = vm.state.new_overlay(ids::Id::empty()).await;
+            let mut db: Box<dyn subnet::rpc::database::Database + Send + Sync> =
+                Box::new(overlay.clone());
+            vm.genesis.load(&mut db).await.map_err(|e| {
+                Error::new(ErrorKind::Other, format!("failed to load genesis: {}", e))
+            })?;
+            vm.state.set_verified_db(genesis_block_id, overlay).await;
+
             vm.state
                 .set_last_accepted(&mut genesis_block)
                 .await
@@ -264,7 +274,6 @@ impl subnet::rpc::common::vm::Vm for ChainVm {
                     Error::new(ErrorKind::Other, format!("failed to accept block: {:?}", e))
                 })?;
 
-            vm.state.set_last_accepted(&mut genesis_block).await?;
             vm.preferred = genesis_block_id;
             log::debug!("initialized from genesis block: {}", genesis_block_id);
         }
@@ -281,6 +290,12 @@ impl subnet::rpc::common::vm::Vm for ChainVm {
             block::builder::Builder::new(inner).build().await;
         });
 
+        // start the sweeper of expired spaces
+        let inner = Arc::clone(&self.inner);
+        tokio::spawn(async move {
+            block::sweeper::Sweeper::new(inner).sweep().await;
+        });
+
         Ok(())
     }
 
@@ -452,11 +467,13 @@ impl subnet::rpc::snowman::block::ChainVm for ChainVm {
         let vm = self.inner.read().await;
 
         let preferred = vm.preferred;
-        let parent = vm
-            .state
-            .get_block(preferred)
-            .await
-            .map_err(|e| Error::new(ErrorKind::Other, format!("failed to get block: {}", e)))?;
+        let parent =
+            match vm.state.get_verified_block(preferred).await {
+                Some(block) => block,
+                None => vm.state.get_block(preferred).await.map_err(|e| {
+                    Error::new(ErrorKind::Other, format!("failed to get block: {}", e))
+                })?,
+            };
 
         log::debug!("vm::build_block parent found!");
         let next_time = Utc::now().timestamp() as u64;
@@ -479,9 +496,23 @@ impl subnet::rpc::snowman::block::ChainVm for ChainVm {
                 txs.push(tx)
             }
         }
-        log::debug!("vm::build_block mempool len: {}", vm.mempool.len());
 
-        block.txs = txs;
+        // txs which no longer execute, such as a second spend of the same
+        // balance, are left out of the block and evicted
+        let failed = block
+            .select_txs(txs)
+            .await
+            .map_err(|e| Error::new(ErrorKind::Other, format!("failed to select txs: {}", e)))?;
+        for tx in failed.iter() {
+            vm.mempool.remove(tx.id);
+        }
+        log::debug!("vm::build_block mempool len: {}", vm.mempool.len());
+        if block.txs.is_empty() {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "no executable txs to build block",
+            ));
+        }
 
         // compute block hash and marshaled representation
         let bytes = block.to_bytes().await;
@@ -70,10 +70,16 @@ async fn e2e() {
     )
     .unwrap();
 
-    // write some random genesis file
+    // write some random genesis file which funds the test key
+    let private_key = get_or_create_pk("/tmp/.spacesvm-cli-pk").expect("generate new private key");
     let genesis = spacesvm::genesis::Genesis {
         author: random_manager::string(5),
         welcome_message: random_manager::string(10),
+        custom_allocation: vec![spacesvm::genesis::CustomAllocation {
+            address: private_key.to_public_key().to_h160(),
+            balance: 10_000_000,
+        }],
+        ..Default::default()
     };
     let genesis_file_path = random_manager::tmp_path(10, None).unwrap();
     genesis.sync(&genesis_file_path).unwrap();
@@ -192,7 +198,6 @@ async fn e2e() {
 
     log::info!("avalanchego RPC endpoints: {:?}", rpc_eps);
 
-    let private_key = get_or_create_pk("/tmp/.spacesvm-cli-pk").expect("generate new private key");
     let chain_url = format!("{}/ext/bc/{}/public", rpc_eps[0], blockchain_id);
     let scli =
         spacesvm::api::client::Client::new(chain_url.parse::<Uri>().expect("valid endpoint"));